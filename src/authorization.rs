@@ -0,0 +1,135 @@
+use crate::capture_command;
+use crate::transaction::AuthRequirement;
+
+/// Resolve the `--source` identity to its public address
+pub fn resolve_source_address(source: &str) -> Result<String, String> {
+    if source.starts_with('G') && source.len() == 56 {
+        return Ok(source.to_string());
+    }
+
+    let command = format!("stellar keys address {}", source);
+    capture_command(&command)
+        .map(|address| address.trim().to_string())
+        .map_err(|e| {
+            format!(
+                "Failed to resolve source account '{}': {}",
+                source,
+                e.trim()
+            )
+        })
+}
+
+/// Addresses that must sign an authorization entry besides the transaction source account
+pub fn required_signers(auth: &[AuthRequirement], source_address: &str) -> Vec<String> {
+    let mut signers: Vec<String> = Vec::new();
+
+    for requirement in auth {
+        if let Some(signer) = &requirement.signer {
+            if signer != source_address && !signers.contains(signer) {
+                signers.push(signer.clone());
+            }
+        }
+    }
+
+    signers
+}
+
+/// Check that the source account can satisfy the authorizations the upgrade requires
+///
+/// An upgrade requiring no authorization at all is refused unless `force` is set.
+pub fn analyze_authorization(
+    auth: &[AuthRequirement],
+    source_address: &str,
+    force: bool,
+) -> Result<(), String> {
+    println!("Analyzing upgrade authorization...");
+
+    if auth.is_empty() {
+        if !force {
+            return Err(
+                "🚨 CRITICAL: The upgrade function does not require any authorization, anyone can replace the code of this contract!\n\
+                Use --force to upgrade anyway, e.g. to replace this code with one protecting its upgrade function."
+                    .to_string(),
+            );
+        }
+        println!("🚨 CRITICAL: The upgrade function does not require any authorization.");
+        println!("🚨 Anyone can replace the code of this contract!");
+        return Ok(());
+    }
+
+    let signers = required_signers(auth, source_address);
+    if signers.is_empty() {
+        println!(
+            "✅ Source account {} can authorize the upgrade",
+            source_address
+        );
+        return Ok(());
+    }
+
+    Err(format!(
+        "❌ The upgrade must be authorized by {} but the source account is {}.\n\
+        Submit the upgrade from the authorizing account, or have it sign the authorization entries before broadcasting.",
+        signers.join(", "),
+        source_address
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "GBZXN7PIRZGNMHGA7MUUUF4GWPY5AYPV6LY4UV2GL6VJGIQRXFDNMADI";
+    const ADMIN: &str = "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54";
+
+    fn requirement(signer: Option<&str>) -> AuthRequirement {
+        AuthRequirement {
+            signer: signer.map(str::to_string),
+            contract: "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX".to_string(),
+            function: "upgrade".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_source_address_public_key() {
+        assert_eq!(resolve_source_address(SOURCE).unwrap(), SOURCE);
+    }
+
+    #[test]
+    fn test_required_signers() {
+        let auth = vec![
+            requirement(Some(ADMIN)),
+            requirement(Some(ADMIN)),
+            requirement(Some(SOURCE)),
+            requirement(None),
+        ];
+
+        assert_eq!(required_signers(&auth, SOURCE), vec![ADMIN.to_string()]);
+    }
+
+    #[test]
+    fn test_analyze_authorization_source_is_admin() {
+        let auth = vec![requirement(Some(SOURCE))];
+        assert!(analyze_authorization(&auth, SOURCE, false).is_ok());
+
+        let auth = vec![requirement(None)];
+        assert!(analyze_authorization(&auth, SOURCE, false).is_ok());
+    }
+
+    #[test]
+    fn test_analyze_authorization_other_signer() {
+        let auth = vec![requirement(Some(ADMIN))];
+
+        let result = analyze_authorization(&auth, SOURCE, false);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains(ADMIN));
+    }
+
+    #[test]
+    fn test_analyze_authorization_no_auth() {
+        let result = analyze_authorization(&[], SOURCE, false);
+        assert!(result.unwrap_err().contains("--force"));
+
+        // Upgrading away from the unprotected code takes --force
+        assert!(analyze_authorization(&[], SOURCE, true).is_ok());
+    }
+}
@@ -0,0 +1,456 @@
+use crate::{contract_id, execute_upgrade, prepare_upgrade, prompt_confirmation, wasm};
+use crate::{BatchArgs, UpgradeArgs};
+use clap::Parser;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Manifest of the contracts to upgrade in a batch
+///
+/// ```toml
+/// [[upgrades]]
+/// id = "token"
+/// wasm_hash = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"
+/// network = "mainnet"
+/// source = "deployer"
+/// migrate = "migrate"
+/// ```
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub upgrades: Vec<ManifestEntry>,
+}
+
+/// An upgrade of the manifest, taking the same defaults as the `upgrade` command
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestEntry {
+    /// Contract ID or alias
+    pub id: String,
+    pub wasm_hash: Option<String>,
+    /// Local WASM file, hashed when `wasm_hash` is omitted
+    pub wasm: Option<String>,
+    pub network: Option<String>,
+    pub source: Option<String>,
+    pub migrate: Option<String>,
+    #[serde(default)]
+    pub migrate_args: BTreeMap<String, String>,
+    /// Additional `upgrade` command line arguments
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl ManifestEntry {
+    /// Build the arguments of the `upgrade` command for this entry
+    pub fn to_upgrade_args(&self) -> Result<UpgradeArgs, String> {
+        let wasm_hash = match (&self.wasm_hash, &self.wasm) {
+            (Some(wasm_hash), _) => wasm_hash.clone(),
+            (None, Some(path)) => {
+                let wasm =
+                    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                wasm::hash_wasm(&wasm)
+            }
+            (None, None) => return Err("either `wasm_hash` or `wasm` is required".to_string()),
+        };
+
+        let mut argv = vec![
+            "upgrade".to_string(),
+            "--id".to_string(),
+            self.id.clone(),
+            "--wasm-hash".to_string(),
+            wasm_hash,
+        ];
+        let options = [
+            ("--wasm", &self.wasm),
+            ("--network", &self.network),
+            ("--source", &self.source),
+            ("--migrate", &self.migrate),
+        ];
+        for (flag, value) in options {
+            if let Some(value) = value {
+                argv.extend([flag.to_string(), value.clone()]);
+            }
+        }
+        for (name, value) in &self.migrate_args {
+            argv.extend(["--migrate-arg".to_string(), format!("{}={}", name, value)]);
+        }
+        argv.extend(self.args.iter().cloned());
+
+        UpgradeArgs::try_parse_from(argv).map_err(|e| e.to_string().trim().to_string())
+    }
+}
+
+/// Parse a manifest, as JSON when the file has a `.json` extension and TOML otherwise
+pub fn parse_manifest(path: &str, content: &str) -> Result<Manifest, String> {
+    if path.ends_with(".json") {
+        serde_json::from_str(content).map_err(|e| format!("Invalid manifest {}: {}", path, e))
+    } else {
+        toml::from_str(content).map_err(|e| format!("Invalid manifest {}: {}", path, e))
+    }
+}
+
+/// Where an upgrade of the batch stands
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Security checks passed, the upgrade is ready to execute
+    Ready(Box<UpgradeArgs>),
+    /// Security checks failed, the upgrade won't be executed
+    Rejected(String),
+    Upgraded,
+    Failed(String),
+    /// Not executed because an earlier upgrade failed
+    Skipped,
+}
+
+/// An upgrade of the batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchItem {
+    /// Contract and network, as displayed in the plan and summary
+    pub label: String,
+    pub wasm_hash: String,
+    /// Security checks the upgrade passed
+    pub checks: Vec<String>,
+    pub outcome: Outcome,
+}
+
+/// Print the upgrades that will be executed and those rejected by the security checks
+pub fn print_plan(items: &[BatchItem]) {
+    println!("\nUpgrade plan:");
+    for (index, item) in items.iter().enumerate() {
+        let status = match &item.outcome {
+            Outcome::Ready(_) => "✅ ready".to_string(),
+            Outcome::Rejected(e) => format!("❌ rejected: {}", e.lines().next().unwrap_or("")),
+            _ => continue,
+        };
+        println!(
+            "  {}. {} -> {} {}",
+            index + 1,
+            item.label,
+            item.wasm_hash,
+            status
+        );
+    }
+    println!();
+}
+
+/// Execute the ready upgrades in order, stopping at the first failure unless told to continue
+pub fn execute_plan(
+    items: &mut [BatchItem],
+    continue_on_failure: bool,
+    mut execute: impl FnMut(&UpgradeArgs, &[String]) -> Result<(), String>,
+) {
+    let mut stopped = false;
+
+    for item in items.iter_mut() {
+        let Outcome::Ready(args) = &item.outcome else {
+            continue;
+        };
+
+        if stopped {
+            item.outcome = Outcome::Skipped;
+            continue;
+        }
+
+        println!("\n=== Upgrading {} ===", item.label);
+        item.outcome = match execute(args, &item.checks) {
+            Ok(()) => Outcome::Upgraded,
+            Err(e) => {
+                stopped = !continue_on_failure;
+                Outcome::Failed(e)
+            }
+        };
+    }
+}
+
+/// Print the outcome of every upgrade, failing when any upgrade didn't happen
+pub fn summarize(items: &[BatchItem]) -> Result<(), String> {
+    println!("\nBatch summary:");
+    for item in items {
+        let status = match &item.outcome {
+            Outcome::Upgraded => "✅ upgraded".to_string(),
+            Outcome::Failed(e) => format!("❌ failed: {}", e.lines().next().unwrap_or("")),
+            Outcome::Rejected(_) => "❌ rejected by security checks".to_string(),
+            Outcome::Skipped => "⏭️  skipped after an earlier failure".to_string(),
+            Outcome::Ready(_) => "⏭️  not executed".to_string(),
+        };
+        println!("  {}: {}", item.label, status);
+    }
+
+    let upgraded = items
+        .iter()
+        .filter(|item| item.outcome == Outcome::Upgraded)
+        .count();
+    if upgraded == items.len() {
+        println!("✅ All {} upgrades succeeded", upgraded);
+        Ok(())
+    } else {
+        Err(format!(
+            "❌ {} of {} upgrades succeeded",
+            upgraded,
+            items.len()
+        ))
+    }
+}
+
+/// Check every upgrade of the manifest, then execute them in order
+pub fn run_batch(args: &BatchArgs, confirm_input: Option<&str>) -> Result<(), String> {
+    let content = std::fs::read_to_string(&args.manifest)
+        .map_err(|e| format!("Failed to read {}: {}", args.manifest, e))?;
+    let manifest = parse_manifest(&args.manifest, &content)?;
+
+    let upgrades = manifest
+        .upgrades
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            entry
+                .to_upgrade_args()
+                .map_err(|e| format!("Invalid upgrade #{} ({}): {}", index + 1, entry.id, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let total = upgrades.len();
+    let mut items: Vec<BatchItem> = upgrades
+        .iter()
+        .enumerate()
+        .map(|(index, upgrade)| {
+            println!(
+                "\n=== Checking upgrade {}/{}: {} on {} ===",
+                index + 1,
+                total,
+                upgrade.id,
+                upgrade.network
+            );
+            let (outcome, checks) = match prepare_upgrade(upgrade, !args.yes) {
+                Ok((prepared, checks)) => (Outcome::Ready(Box::new(prepared)), checks),
+                Err(e) => {
+                    println!("{}", e);
+                    (Outcome::Rejected(e), Vec::new())
+                }
+            };
+            let label = match &outcome {
+                Outcome::Ready(prepared) => contract_id::display_contract(prepared),
+                _ => upgrade.id.clone(),
+            };
+
+            BatchItem {
+                label: format!("{} on {}", label, upgrade.network),
+                wasm_hash: upgrade.wasm_hash.clone(),
+                checks,
+                outcome,
+            }
+        })
+        .collect();
+
+    print_plan(&items);
+
+    let rejected = items
+        .iter()
+        .filter(|item| matches!(item.outcome, Outcome::Rejected(_)))
+        .count();
+    if rejected > 0 && !args.continue_on_failure {
+        return Err(format!(
+            "❌ {} of {} upgrades failed their security checks, nothing was executed. \
+            Use --continue-on-failure to execute the others.",
+            rejected, total
+        ));
+    }
+
+    let ready = total - rejected;
+    if ready == 0 {
+        return summarize(&items);
+    }
+    let confirmed = match confirm_input {
+        Some(input) => matches!(input.trim().to_lowercase().as_str(), "y" | "yes"),
+        None => args.yes || prompt_confirmation(&format!("Execute {} upgrades?", ready))?,
+    };
+    if !confirmed {
+        return Err("Batch upgrade cancelled by user".to_string());
+    }
+
+    execute_plan(&mut items, args.continue_on_failure, execute_upgrade);
+    summarize(&items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+
+    fn ready_item(id: &str) -> BatchItem {
+        let args = ManifestEntry {
+            id: id.to_string(),
+            wasm_hash: Some(HASH.to_string()),
+            ..Default::default()
+        }
+        .to_upgrade_args()
+        .unwrap();
+
+        BatchItem {
+            label: id.to_string(),
+            wasm_hash: HASH.to_string(),
+            checks: vec!["Version Check".to_string()],
+            outcome: Outcome::Ready(Box::new(args)),
+        }
+    }
+
+    #[test]
+    fn test_parse_manifest_toml() {
+        let manifest = parse_manifest(
+            "upgrades.toml",
+            &format!(
+                r#"
+                [[upgrades]]
+                id = "token"
+                wasm_hash = "{}"
+                network = "mainnet"
+                migrate = "migrate"
+                migrate_args = {{ version = "2" }}
+
+                [[upgrades]]
+                id = "vault"
+                wasm = "target/vault.wasm"
+                "#,
+                HASH
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(manifest.upgrades.len(), 2);
+        assert_eq!(manifest.upgrades[0].network.as_deref(), Some("mainnet"));
+        assert_eq!(manifest.upgrades[0].migrate_args["version"], "2");
+        assert_eq!(
+            manifest.upgrades[1].wasm.as_deref(),
+            Some("target/vault.wasm")
+        );
+    }
+
+    #[test]
+    fn test_parse_manifest_json() {
+        let manifest = parse_manifest(
+            "upgrades.json",
+            &format!(
+                r#"{{"upgrades": [{{"id": "token", "wasm_hash": "{}", "source": "deployer"}}]}}"#,
+                HASH
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(manifest.upgrades[0].source.as_deref(), Some("deployer"));
+        assert!(parse_manifest("upgrades.json", r#"{"upgrades": [{"name": "x"}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_to_upgrade_args() {
+        let entry = ManifestEntry {
+            id: "token".to_string(),
+            wasm_hash: Some(HASH.to_uppercase()),
+            network: Some("mainnet".to_string()),
+            migrate: Some("migrate".to_string()),
+            migrate_args: BTreeMap::from([("version".to_string(), "2".to_string())]),
+            args: vec!["--allow-two-step".to_string()],
+            ..Default::default()
+        };
+
+        let args = entry.to_upgrade_args().unwrap();
+        assert_eq!(args.id, "token");
+        assert_eq!(args.wasm_hash, HASH);
+        assert_eq!(args.network, "mainnet");
+        assert_eq!(args.source, "alice");
+        assert_eq!(args.migrate.as_deref(), Some("migrate"));
+        assert_eq!(
+            args.migrate_args,
+            vec![("version".to_string(), "2".to_string())]
+        );
+        assert!(args.allow_two_step);
+    }
+
+    #[test]
+    fn test_to_upgrade_args_invalid() {
+        let entry = ManifestEntry {
+            id: "token".to_string(),
+            ..Default::default()
+        };
+        assert!(entry.to_upgrade_args().unwrap_err().contains("wasm_hash"));
+
+        let entry = ManifestEntry {
+            id: "token".to_string(),
+            wasm_hash: Some("abc".to_string()),
+            ..Default::default()
+        };
+        assert!(entry.to_upgrade_args().is_err());
+    }
+
+    #[test]
+    fn test_execute_plan_stops_on_failure() {
+        let mut items = vec![ready_item("a"), ready_item("b"), ready_item("c")];
+        items[1].outcome = Outcome::Rejected("checks failed".to_string());
+        items.push(ready_item("d"));
+
+        execute_plan(&mut items, false, |args, _| {
+            if args.id == "c" {
+                Err("transaction failed".to_string())
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(items[0].outcome, Outcome::Upgraded);
+        assert_eq!(
+            items[1].outcome,
+            Outcome::Rejected("checks failed".to_string())
+        );
+        assert_eq!(
+            items[2].outcome,
+            Outcome::Failed("transaction failed".to_string())
+        );
+        assert_eq!(items[3].outcome, Outcome::Skipped);
+        assert!(summarize(&items).unwrap_err().contains("1 of 4"));
+    }
+
+    #[test]
+    fn test_execute_plan_continues_on_failure() {
+        let mut items = vec![ready_item("a"), ready_item("b"), ready_item("c")];
+
+        execute_plan(&mut items, true, |args, checks| {
+            assert_eq!(checks, ["Version Check"]);
+            if args.id == "a" {
+                Err("transaction failed".to_string())
+            } else {
+                Ok(())
+            }
+        });
+
+        assert!(matches!(items[0].outcome, Outcome::Failed(_)));
+        assert_eq!(items[1].outcome, Outcome::Upgraded);
+        assert_eq!(items[2].outcome, Outcome::Upgraded);
+    }
+
+    #[test]
+    fn test_run_batch_invalid_manifest() {
+        let path = std::env::temp_dir().join("stellar-upgrader-test-batch.toml");
+        std::fs::write(&path, "[[upgrades]]\nid = \"token\"\n").unwrap();
+        let args = BatchArgs {
+            manifest: path.to_str().unwrap().to_string(),
+            continue_on_failure: false,
+            yes: true,
+        };
+
+        let result = run_batch(&args, None);
+        assert!(result
+            .unwrap_err()
+            .starts_with("Invalid upgrade #1 (token): either `wasm_hash` or `wasm` is required"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(run_batch(&args, None)
+            .unwrap_err()
+            .starts_with("Failed to read"));
+    }
+
+    #[test]
+    fn test_summarize_success() {
+        let mut items = vec![ready_item("a")];
+        items[0].outcome = Outcome::Upgraded;
+        assert!(summarize(&items).is_ok());
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Project configuration file, read from the current directory
+pub const CONFIG_FILE: &str = "stellar-upgrader.toml";
+
+/// Project configuration of the upgrader
+///
+/// ```toml
+/// [aliases.mainnet]
+/// token = "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX"
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Contract aliases, by network then name
+    #[serde(default)]
+    pub aliases: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Config {
+    /// Contract ID of an alias on a network
+    pub fn alias(&self, name: &str, network: &str) -> Option<&String> {
+        self.aliases.get(network)?.get(name)
+    }
+
+    /// Parse a configuration file content
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("Invalid {}: {}", CONFIG_FILE, e))
+    }
+
+    /// Load the configuration file at `path`, if it exists
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Config::parse(&content)
+    }
+
+    /// Load the configuration file of the current directory, if any
+    pub fn load() -> Result<Self, String> {
+        Config::load_from(Path::new(CONFIG_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            [aliases.mainnet]
+            token = "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.alias("token", "mainnet").map(String::as_str),
+            Some("CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX")
+        );
+        assert_eq!(config.alias("token", "testnet"), None);
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::parse("[unknown]").is_err());
+
+        // Aliases must be scoped to a network
+        assert!(Config::parse(
+            r#"
+            [aliases]
+            token = "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX"
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_load_missing_config() {
+        let config = Config::load_from(Path::new("/nonexistent/stellar-upgrader.toml")).unwrap();
+        assert!(config.aliases.is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::config::{Config, CONFIG_FILE};
+use crate::{capture_command, UpgradeArgs};
+
+/// Strkey version byte of contract addresses, encoding to a leading `C`
+const CONTRACT_VERSION_BYTE: u8 = 2 << 3;
+
+/// Strkey version byte of ed25519 public keys, encoding to a leading `G`
+const ACCOUNT_VERSION_BYTE: u8 = 6 << 3;
+
+/// Length of an encoded strkey: version byte, 32-byte payload and 2-byte checksum
+const STRKEY_LENGTH: usize = 56;
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// CRC16-XModem checksum of a strkey
+fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Decode unpadded RFC 4648 base32
+fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in s.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(bytes)
+}
+
+/// Whether the value is shaped like a strkey, as opposed to an alias
+fn looks_like_strkey(s: &str) -> bool {
+    s.len() == STRKEY_LENGTH
+        && s.bytes()
+            .all(|c| c.is_ascii_uppercase() || (b'2'..=b'7').contains(&c))
+}
+
+/// Validate a `C...` contract address, checksum included
+pub fn validate_contract_id(id: &str) -> Result<(), String> {
+    if !looks_like_strkey(id) {
+        return Err(format!(
+            "`{}` is not a contract ID: expected {} characters of base32",
+            id, STRKEY_LENGTH
+        ));
+    }
+    if !id.starts_with('C') {
+        return Err(format!(
+            "`{}` is not a contract ID: contract IDs start with C",
+            id
+        ));
+    }
+
+    let bytes = decode_base32(id).ok_or_else(|| format!("`{}` is not valid base32", id))?;
+    let (data, checksum) = bytes.split_at(bytes.len() - 2);
+    if data[0] != CONTRACT_VERSION_BYTE {
+        return Err(format!("`{}` is not a contract ID", id));
+    }
+    if crc16_xmodem(data) != u16::from_le_bytes([checksum[0], checksum[1]]) {
+        return Err(format!(
+            "`{}` has an invalid checksum, check the contract ID for typos",
+            id
+        ));
+    }
+
+    Ok(())
+}
+
+/// Decode a `G...` account ID into its ed25519 public key, checksum included
+pub fn decode_account_id(id: &str) -> Result<[u8; 32], String> {
+    if !looks_like_strkey(id) || !id.starts_with('G') {
+        return Err(format!("`{}` is not an account ID", id));
+    }
+
+    let bytes = decode_base32(id).ok_or_else(|| format!("`{}` is not valid base32", id))?;
+    let (data, checksum) = bytes.split_at(bytes.len() - 2);
+    if data[0] != ACCOUNT_VERSION_BYTE
+        || crc16_xmodem(data) != u16::from_le_bytes([checksum[0], checksum[1]])
+    {
+        return Err(format!("`{}` is not a valid account ID", id));
+    }
+
+    data[1..]
+        .try_into()
+        .map_err(|_| format!("`{}` is not a valid account ID", id))
+}
+
+/// Look up an alias in the Stellar CLI's contract alias store
+pub(crate) fn show_cli_alias(alias: &str, network: &str) -> Option<String> {
+    let command = format!(
+        "stellar contract alias show {} --network {}",
+        alias, network
+    );
+    capture_command(&command)
+        .ok()
+        .map(|output| output.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// Resolve a contract ID or alias to a validated contract ID
+///
+/// Aliases are looked up in the config file first, then in the Stellar CLI's alias store.
+/// Returns the contract ID, and the alias when one was resolved.
+pub fn resolve_contract_id(
+    value: &str,
+    network: &str,
+    config: &Config,
+    cli_alias: impl Fn(&str, &str) -> Option<String>,
+) -> Result<(String, Option<String>), String> {
+    if looks_like_strkey(value) {
+        validate_contract_id(value)?;
+        return Ok((value.to_string(), None));
+    }
+
+    // Both stores are scoped to the network, and must agree when they both know the alias
+    let id = match (config.alias(value, network), cli_alias(value, network)) {
+        (Some(config_id), Some(cli_id)) if *config_id != cli_id => {
+            return Err(format!(
+                "Alias `{}` resolves to {} in {} but to {} in the Stellar CLI on {}",
+                value, config_id, CONFIG_FILE, cli_id, network
+            ))
+        }
+        (Some(config_id), _) => config_id.clone(),
+        (None, Some(cli_id)) => cli_id,
+        (None, None) => {
+            return Err(format!(
+                "`{}` is neither a contract ID nor a known alias on {}",
+                value, network
+            ))
+        }
+    };
+
+    validate_contract_id(&id)
+        .map_err(|e| format!("Alias `{}` resolves to an invalid ID: {}", value, e))?;
+    Ok((id, Some(value.to_string())))
+}
+
+/// Resolve the contract and router IDs of the upgrade, which may be aliases
+pub fn resolve_args(args: &UpgradeArgs) -> Result<UpgradeArgs, String> {
+    let config = Config::load()?;
+
+    let (id, id_alias) = resolve_contract_id(&args.id, &args.network, &config, show_cli_alias)?;
+    let router = match &args.router {
+        Some(router) => {
+            Some(resolve_contract_id(router, &args.network, &config, show_cli_alias)?.0)
+        }
+        None => None,
+    };
+
+    Ok(UpgradeArgs {
+        id,
+        id_alias,
+        router,
+        ..args.clone()
+    })
+}
+
+/// Describe the upgraded contract by alias and ID
+pub fn display_contract(args: &UpgradeArgs) -> String {
+    match &args.id_alias {
+        Some(alias) => format!("{} ({})", alias, args.id),
+        None => args.id.clone(),
+    }
+}
+
+/// Print the contract, WASM and network the upgrade targets
+pub fn print_target(args: &UpgradeArgs) {
+    println!(
+        "Upgrading contract {} to WASM {} on {}\n",
+        display_contract(args),
+        args.wasm_hash,
+        args.network
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACT: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+    const ACCOUNT: &str = "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54";
+    const OTHER_CONTRACT: &str = "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX";
+
+    fn no_cli_alias(_: &str, _: &str) -> Option<String> {
+        None
+    }
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: CONTRACT.to_string(),
+            wasm_hash: "test_hash".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    #[test]
+    fn test_validate_contract_id() {
+        assert!(validate_contract_id(CONTRACT).is_ok());
+
+        // Typo in the last character
+        let typo = format!("{}N", &CONTRACT[..55]);
+        assert!(validate_contract_id(&typo)
+            .unwrap_err()
+            .contains("invalid checksum"));
+
+        assert!(validate_contract_id(ACCOUNT)
+            .unwrap_err()
+            .contains("start with C"));
+        assert!(validate_contract_id("CABC").is_err());
+        assert!(validate_contract_id(&CONTRACT.to_lowercase()).is_err());
+    }
+
+    #[test]
+    fn test_decode_account_id() {
+        let key = decode_account_id(ACCOUNT).unwrap();
+        assert_eq!(&key[..2], &[0xc1, 0x3e]);
+
+        assert!(decode_account_id(CONTRACT).is_err());
+        let typo = format!("{}A", &ACCOUNT[..55]);
+        assert!(decode_account_id(&typo).is_err());
+    }
+
+    #[test]
+    fn test_resolve_contract_id() {
+        let (id, alias) =
+            resolve_contract_id(CONTRACT, "testnet", &Config::default(), no_cli_alias).unwrap();
+        assert_eq!(id, CONTRACT);
+        assert_eq!(alias, None);
+    }
+
+    #[test]
+    fn test_resolve_alias_from_config() {
+        let config =
+            Config::parse(&format!("[aliases.testnet]\ntoken = \"{}\"", CONTRACT)).unwrap();
+
+        let (id, alias) = resolve_contract_id("token", "testnet", &config, no_cli_alias).unwrap();
+        assert_eq!(id, CONTRACT);
+        assert_eq!(alias.as_deref(), Some("token"));
+
+        // The alias of another network doesn't apply
+        assert!(resolve_contract_id("token", "mainnet", &config, no_cli_alias).is_err());
+    }
+
+    #[test]
+    fn test_resolve_alias_conflict() {
+        let config =
+            Config::parse(&format!("[aliases.mainnet]\ntoken = \"{}\"", CONTRACT)).unwrap();
+        let same = |_: &str, _: &str| Some(CONTRACT.to_string());
+        let other = |_: &str, _: &str| Some(OTHER_CONTRACT.to_string());
+
+        assert!(resolve_contract_id("token", "mainnet", &config, same).is_ok());
+        assert!(resolve_contract_id("token", "mainnet", &config, other)
+            .unwrap_err()
+            .contains("in the Stellar CLI on mainnet"));
+    }
+
+    #[test]
+    fn test_resolve_alias_from_cli() {
+        let cli_alias = |alias: &str, network: &str| {
+            (alias == "token" && network == "mainnet").then(|| CONTRACT.to_string())
+        };
+
+        let (id, _) =
+            resolve_contract_id("token", "mainnet", &Config::default(), cli_alias).unwrap();
+        assert_eq!(id, CONTRACT);
+
+        assert!(
+            resolve_contract_id("token", "testnet", &Config::default(), cli_alias)
+                .unwrap_err()
+                .contains("neither a contract ID nor a known alias on testnet")
+        );
+    }
+
+    #[test]
+    fn test_resolve_alias_to_invalid_id() {
+        let config = Config::parse(&format!("[aliases.testnet]\nadmin = \"{}\"", ACCOUNT)).unwrap();
+
+        assert!(resolve_contract_id("admin", "testnet", &config, no_cli_alias).is_err());
+    }
+
+    #[test]
+    fn test_display_contract() {
+        let mut args = create_test_args();
+        assert_eq!(display_contract(&args), CONTRACT);
+
+        args.id_alias = Some("token".to_string());
+        assert_eq!(display_contract(&args), format!("token ({})", CONTRACT));
+    }
+}
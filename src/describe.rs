@@ -0,0 +1,380 @@
+use crate::config::Config;
+use crate::history::format_timestamp;
+use crate::security_checks;
+use crate::transaction::{self, as_u64, AuthRequirement, Bounds};
+use crate::{contract_id, DescribeTxArgs, UpgradeArgs};
+use clap::Parser;
+use serde_json::Value;
+use std::path::Path;
+
+/// What a transaction envelope does, decoded for review before signing
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionSummary {
+    pub source_account: String,
+    /// Maximum fee, in stroops
+    pub fee: u64,
+    pub sequence: u64,
+    pub memo: Value,
+    pub bounds: Bounds,
+    pub contract: String,
+    pub function: String,
+    pub args: Vec<Value>,
+    /// WASM hash passed to the function, the first 32-byte argument
+    pub wasm_hash: Option<String>,
+    pub auth: Vec<AuthRequirement>,
+    /// Part of the fee paid for Soroban resources, when the transaction was simulated
+    pub resource_fee: Option<u64>,
+    pub signatures: usize,
+}
+
+impl TransactionSummary {
+    /// Summarize a decoded transaction envelope, which must invoke a single contract function
+    pub fn from_envelope(envelope: &Value) -> Result<Self, String> {
+        let tx = transaction::transaction(envelope)?;
+        let invoke = transaction::invoke_host_function(tx)?;
+        let call = invoke["host_function"]
+            .get("invoke_contract")
+            .ok_or_else(|| "Transaction doesn't invoke a contract function".to_string())?;
+
+        let args = call["args"].as_array().cloned().unwrap_or_default();
+        let wasm_hash = args.iter().find_map(|arg| {
+            arg["bytes"]
+                .as_str()
+                .filter(|bytes| bytes.len() == 64)
+                .map(str::to_lowercase)
+        });
+
+        Ok(TransactionSummary {
+            source_account: tx["source_account"].as_str().unwrap_or("?").to_string(),
+            fee: as_u64(&tx["fee"]).unwrap_or(0),
+            sequence: as_u64(&tx["seq_num"]).unwrap_or(0),
+            memo: tx["memo"].clone(),
+            bounds: transaction::bounds(tx),
+            contract: call["contract_address"].as_str().unwrap_or("?").to_string(),
+            function: call["function_name"].as_str().unwrap_or("?").to_string(),
+            args,
+            wasm_hash,
+            auth: transaction::auth_requirements(invoke),
+            resource_fee: tx["ext"]
+                .get("v1")
+                .and_then(|soroban_data| as_u64(&soroban_data["resource_fee"])),
+            signatures: envelope["tx"]["signatures"]
+                .as_array()
+                .map(Vec::len)
+                .unwrap_or(0),
+        })
+    }
+
+    /// Describe how the transaction differs from the expected upgrade
+    pub fn mismatches(
+        &self,
+        contract: Option<&str>,
+        function: &str,
+        wasm_hash: Option<&str>,
+    ) -> Vec<String> {
+        let mut mismatches = Vec::new();
+
+        if let Some(contract) = contract {
+            if self.contract != contract {
+                mismatches.push(format!(
+                    "invokes contract {}, expected {}",
+                    self.contract, contract
+                ));
+            }
+        }
+        if self.function != function {
+            mismatches.push(format!(
+                "calls `{}`, expected `{}`",
+                self.function, function
+            ));
+        }
+        match (&self.wasm_hash, wasm_hash) {
+            (None, _) => mismatches.push("passes no WASM hash".to_string()),
+            (Some(found), Some(expected)) if found != expected => {
+                mismatches.push(format!("passes WASM hash {}, expected {}", found, expected))
+            }
+            _ => {}
+        }
+
+        mismatches
+    }
+
+    /// Print the summary
+    pub fn print(&self) {
+        let describe_time = |time: Option<u64>| match time {
+            Some(time) => format_timestamp(time),
+            None => "unbounded".to_string(),
+        };
+        let describe_ledger = |ledger: Option<u64>| match ledger {
+            Some(ledger) => ledger.to_string(),
+            None => "unbounded".to_string(),
+        };
+
+        println!("Transaction:");
+        println!("  Source:      {}", self.source_account);
+        println!("  Sequence:    {}", self.sequence);
+        match self.resource_fee {
+            Some(resource_fee) => println!(
+                "  Fee:         {} stroops ({} resource fee)",
+                self.fee, resource_fee
+            ),
+            None => println!("  Fee:         {} stroops (not simulated)", self.fee),
+        }
+        if self.memo != "none" {
+            println!("  Memo:        {}", self.memo);
+        }
+        if self.bounds == Bounds::default() {
+            println!("  Bounds:      none");
+        } else {
+            println!(
+                "  Time:        {} to {}",
+                describe_time(self.bounds.min_time),
+                describe_time(self.bounds.max_time)
+            );
+            println!(
+                "  Ledgers:     {} to {}",
+                describe_ledger(self.bounds.min_ledger),
+                describe_ledger(self.bounds.max_ledger)
+            );
+        }
+        println!("  Signatures:  {}", self.signatures);
+
+        println!("Invocation:");
+        println!("  Contract:    {}", self.contract);
+        println!("  Function:    {}", self.function);
+        for arg in &self.args {
+            println!("  Argument:    {}", arg);
+        }
+
+        if self.auth.is_empty() {
+            println!("Authorizations: none");
+        } else {
+            println!("Authorizations:");
+            for auth in &self.auth {
+                println!(
+                    "  {} authorizes {}.{}",
+                    auth.signer.as_deref().unwrap_or("source account"),
+                    auth.contract,
+                    auth.function
+                );
+            }
+        }
+    }
+}
+
+/// Read the envelope given inline, in a file, or in a transaction file written by `build`
+pub fn read_envelope_input(input: &str) -> Result<String, String> {
+    let content = if Path::new(input).is_file() {
+        std::fs::read_to_string(input).map_err(|e| format!("Failed to read {}: {}", input, e))?
+    } else {
+        input.to_string()
+    };
+    let content = content.trim();
+
+    if content.starts_with('{') {
+        let file: Value = serde_json::from_str(content)
+            .map_err(|e| format!("Invalid transaction file {}: {}", input, e))?;
+        return file["envelope"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("No envelope in transaction file {}", input));
+    }
+
+    Ok(content.to_string())
+}
+
+/// Upgrade arguments performing the upgrade found in the transaction, to check it
+fn upgrade_args(
+    summary: &TransactionSummary,
+    args: &DescribeTxArgs,
+) -> Result<UpgradeArgs, String> {
+    let wasm_hash = summary
+        .wasm_hash
+        .as_ref()
+        .ok_or_else(|| "❌ No WASM hash in the transaction to check".to_string())?;
+
+    let mut argv = vec![
+        "upgrade",
+        "--id",
+        &summary.contract,
+        "--wasm-hash",
+        wasm_hash,
+        "--source",
+        &summary.source_account,
+        "--network",
+        &args.network,
+        "--upgrade-fn",
+        &summary.function,
+    ];
+    if let Some(sandbox) = &args.sandbox_network {
+        argv.extend(["--sandbox-network", sandbox]);
+    }
+    for check in &args.skip_checks {
+        argv.extend(["--skip-check", check]);
+    }
+    UpgradeArgs::try_parse_from(argv).map_err(|e| e.to_string().trim().to_string())
+}
+
+/// Run the describe-tx command
+pub fn run_describe_tx(args: &DescribeTxArgs) -> Result<(), String> {
+    let xdr = read_envelope_input(&args.xdr)?;
+    let envelope = transaction::decode_envelope(&xdr)?;
+    let summary = TransactionSummary::from_envelope(&envelope)?;
+    summary.print();
+    println!();
+
+    let contract = match &args.id {
+        Some(id) => Some(
+            contract_id::resolve_contract_id(
+                id,
+                &args.network,
+                &Config::load()?,
+                contract_id::show_cli_alias,
+            )?
+            .0,
+        ),
+        None => None,
+    };
+    let function = args.upgrade_fn.as_deref().unwrap_or("upgrade");
+
+    let mismatches = summary.mismatches(contract.as_deref(), function, args.wasm_hash.as_deref());
+    if !mismatches.is_empty() {
+        return Err(format!(
+            "❌ The transaction isn't the expected upgrade, it:\n  - {}",
+            mismatches.join("\n  - ")
+        ));
+    }
+    println!(
+        "✅ Transaction calls `{}` on {} with WASM {}",
+        summary.function,
+        summary.contract,
+        summary.wasm_hash.as_deref().unwrap_or_default()
+    );
+
+    if args.check {
+        println!();
+        security_checks::run_all_checks(&upgrade_args(&summary, args)?)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACT: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+    const HASH: &str = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+    const SOURCE: &str = "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54";
+
+    fn create_test_args() -> DescribeTxArgs {
+        DescribeTxArgs {
+            xdr: "AAAAAgAAAAA=".to_string(),
+            id: None,
+            wasm_hash: None,
+            upgrade_fn: None,
+            network: "testnet".to_string(),
+            check: false,
+            sandbox_network: Some("local".to_string()),
+            skip_checks: vec!["ttl".to_string()],
+        }
+    }
+
+    fn envelope() -> Value {
+        serde_json::json!({
+            "tx": {
+                "tx": {
+                    "source_account": SOURCE,
+                    "fee": 123456,
+                    "seq_num": "4294967297",
+                    "cond": { "time": { "min_time": "1700000000", "max_time": "0" } },
+                    "memo": "none",
+                    "operations": [{
+                        "body": {
+                            "invoke_host_function": {
+                                "host_function": {
+                                    "invoke_contract": {
+                                        "contract_address": CONTRACT,
+                                        "function_name": "upgrade",
+                                        "args": [{ "bytes": HASH.to_uppercase() }]
+                                    }
+                                },
+                                "auth": []
+                            }
+                        }
+                    }],
+                    "ext": { "v1": { "resource_fee": "123356" } }
+                },
+                "signatures": [{ "hint": "00000000", "signature": "00" }]
+            }
+        })
+    }
+
+    #[test]
+    fn test_summary_from_envelope() {
+        let summary = TransactionSummary::from_envelope(&envelope()).unwrap();
+
+        assert_eq!(summary.source_account, SOURCE);
+        assert_eq!(summary.fee, 123456);
+        assert_eq!(summary.sequence, 4294967297);
+        assert_eq!(summary.bounds.min_time, Some(1_700_000_000));
+        assert_eq!(summary.contract, CONTRACT);
+        assert_eq!(summary.function, "upgrade");
+        assert_eq!(summary.wasm_hash.as_deref(), Some(HASH));
+        assert_eq!(summary.resource_fee, Some(123356));
+        assert_eq!(summary.signatures, 1);
+    }
+
+    #[test]
+    fn test_summary_not_a_contract_call() {
+        let mut envelope = envelope();
+        envelope["tx"]["tx"]["operations"][0]["body"] = serde_json::json!({ "payment": {} });
+
+        assert!(TransactionSummary::from_envelope(&envelope).is_err());
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let summary = TransactionSummary::from_envelope(&envelope()).unwrap();
+
+        assert!(summary
+            .mismatches(Some(CONTRACT), "upgrade", Some(HASH))
+            .is_empty());
+        assert!(summary.mismatches(None, "upgrade", None).is_empty());
+
+        let mismatches = summary.mismatches(Some("COTHER"), "set_code", Some(&"1".repeat(64)));
+        assert_eq!(mismatches.len(), 3);
+        assert!(mismatches[0].contains("invokes contract"));
+        assert!(mismatches[1].contains("calls `upgrade`, expected `set_code`"));
+        assert!(mismatches[2].contains("passes WASM hash"));
+    }
+
+    #[test]
+    fn test_read_envelope_input() {
+        assert_eq!(
+            read_envelope_input(" AAAAAgAAAAA=\n").unwrap(),
+            "AAAAAgAAAAA="
+        );
+
+        let path = std::env::temp_dir().join(format!("describe-tx-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"format":1,"envelope":"AAAAAgAAAAA="}"#).unwrap();
+        assert_eq!(
+            read_envelope_input(path.to_str().unwrap()).unwrap(),
+            "AAAAAgAAAAA="
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_args() {
+        let summary = TransactionSummary::from_envelope(&envelope()).unwrap();
+
+        let upgrade = upgrade_args(&summary, &create_test_args()).unwrap();
+        assert_eq!(upgrade.id, CONTRACT);
+        assert_eq!(upgrade.wasm_hash, HASH);
+        assert_eq!(upgrade.source, SOURCE);
+        assert_eq!(upgrade.upgrade_fn.as_deref(), Some("upgrade"));
+        assert_eq!(upgrade.sandbox_network.as_deref(), Some("local"));
+        assert_eq!(upgrade.skip_checks, vec!["ttl".to_string()]);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::config::Config;
+use crate::history::HistoryEntry;
+use crate::security_checks::contract_info::{self, UpgradeEntrypoint};
+use crate::{
+    capture_command, contract_id, execute_command, generate_invoke_command, history, is_submitted,
+    prepare_upgrade, verification, wasm, ExecuteArgs, GovernorArgs, ProposeArgs, UpgradeArgs,
+};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Contract invoked by the upgrade call, the router for routed upgrades
+fn upgrade_target(args: &UpgradeArgs) -> &str {
+    args.router.as_deref().unwrap_or(&args.id)
+}
+
+/// Salt identifying the upgrade operation, the same when proposing and executing it
+///
+/// The payload is included, so upgrades with different arguments are different operations.
+pub fn operation_salt(args: &UpgradeArgs, payload: &str) -> String {
+    let operation = format!(
+        "{}:{}:{}:{}",
+        upgrade_target(args),
+        args.upgrade_fn.as_deref().unwrap_or("upgrade"),
+        args.wasm_hash,
+        payload
+    );
+    format!("{:x}", Sha256::digest(operation.as_bytes()))
+}
+
+/// Encode a `--upgrade-arg` value as an `ScVal` JSON
+///
+/// Account and contract IDs are encoded as addresses, other values must already be `ScVal` JSON.
+fn encode_value(name: &str, value: &str) -> Result<Value, String> {
+    if contract_id::validate_contract_id(value).is_ok()
+        || contract_id::decode_account_id(value).is_ok()
+    {
+        return Ok(serde_json::json!({ "address": value }));
+    }
+
+    serde_json::from_str::<Value>(value)
+        .ok()
+        .filter(Value::is_object)
+        .ok_or_else(|| {
+            format!(
+                "❌ Can't encode upgrade argument `{}` for the governor, pass it as ScVal JSON (e.g. {}='{{\"u32\":1}}')",
+                name, name
+            )
+        })
+}
+
+/// Encode the arguments of the upgrade call as a JSON `Vec<Val>`
+///
+/// The governor replays the arguments by position, so they follow the declaration order of
+/// the entrypoint's parameters.
+pub fn encode_payload(
+    args: &UpgradeArgs,
+    entrypoint: &UpgradeEntrypoint,
+) -> Result<String, String> {
+    if !args.contract_args.is_empty() {
+        return Err(
+            "❌ Arguments after `--` can't be encoded for the governor, use --upgrade-arg instead"
+                .to_string(),
+        );
+    }
+    if let Some((name, _)) = args.upgrade_args.iter().find(|(name, _)| {
+        !entrypoint
+            .extra_params
+            .iter()
+            .any(|param| &param.name == name)
+    }) {
+        return Err(format!(
+            "❌ `{}` has no parameter `{}`",
+            entrypoint.function, name
+        ));
+    }
+
+    let mut payload = Vec::new();
+    for param in &entrypoint.params {
+        if param.name == entrypoint.hash_param {
+            payload.push(serde_json::json!({ "bytes": args.wasm_hash }));
+            continue;
+        }
+        let value = args
+            .upgrade_args
+            .iter()
+            .find(|(name, _)| name == &param.name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| {
+                format!(
+                    "❌ Missing value for argument `{}` of `{}`, pass it with --upgrade-arg",
+                    param.name, entrypoint.function
+                )
+            })?;
+        payload.push(encode_value(&param.name, value)?);
+    }
+
+    serde_json::to_string(&payload).map_err(|e| format!("Failed to encode the payload: {}", e))
+}
+
+/// Generate the command invoking `function` on the governor with the upgrade call as payload
+pub fn generate_governor_command(
+    args: &UpgradeArgs,
+    governor: &GovernorArgs,
+    entrypoint: &UpgradeEntrypoint,
+    function: &str,
+    delay: Option<u32>,
+) -> Result<String, String> {
+    let payload = encode_payload(args, entrypoint)?;
+    let mut command = generate_invoke_command(args, &governor.governor);
+    if args.build_only {
+        command.push_str(" --build-only");
+    }
+    if let Some(send) = &args.send {
+        command.push_str(&format!(" --send {}", send));
+    }
+
+    command.push_str(&format!(
+        " -- {} --target {} --function {} --args '{}' --salt {}",
+        function,
+        upgrade_target(args),
+        args.upgrade_fn.as_deref().unwrap_or("upgrade"),
+        payload,
+        operation_salt(args, &payload)
+    ));
+    if let Some(delay) = delay {
+        command.push_str(&format!(" --delay {}", delay));
+    }
+    for (name, value) in &governor.governor_args {
+        command.push_str(&format!(" --{} {}", name, value));
+    }
+
+    Ok(command)
+}
+
+/// Find the entrypoint the governor calls, on the router for routed upgrades
+fn fetch_entrypoint(args: &UpgradeArgs) -> Result<UpgradeEntrypoint, String> {
+    let target = UpgradeArgs {
+        id: upgrade_target(args).to_string(),
+        ..args.clone()
+    };
+    let interface = contract_info::fetch_deployed_interface(&target)?;
+    contract_info::find_upgrade_entrypoint(
+        &contract_info::parse_functions(&interface),
+        args.upgrade_fn.as_deref(),
+        args.hash_arg.as_deref(),
+    )
+}
+
+/// Resolve the governor and run the security checks on the upgrade it will perform
+fn prepare_governed_upgrade(
+    governor: &GovernorArgs,
+    upgrade: &UpgradeArgs,
+) -> Result<(GovernorArgs, UpgradeArgs, UpgradeEntrypoint, Vec<String>), String> {
+    if upgrade.force {
+        return Err(
+            "A governed upgrade runs the security checks when proposed and executed, it can't use --force"
+                .to_string(),
+        );
+    }
+
+    let (governor_id, _) = contract_id::resolve_contract_id(
+        &governor.governor,
+        &upgrade.network,
+        &Config::load()?,
+        contract_id::show_cli_alias,
+    )?;
+    let governor = GovernorArgs {
+        governor: governor_id,
+        ..governor.clone()
+    };
+
+    let (upgrade, checks) = prepare_upgrade(upgrade, true)?;
+    if upgrade.migrate.is_some() {
+        return Err(
+            "❌ A two-step migration needs a second call the governor doesn't make. \
+            Upgrade and migrate through an atomic entrypoint instead."
+                .to_string(),
+        );
+    }
+
+    let entrypoint = fetch_entrypoint(&upgrade)?;
+    Ok((governor, upgrade, entrypoint, checks))
+}
+
+/// Run the propose command, scheduling the checked upgrade on the governor
+pub fn run_propose(args: &ProposeArgs) -> Result<(), String> {
+    let (governor, upgrade, entrypoint, _) =
+        prepare_governed_upgrade(&args.governor, &args.upgrade)?;
+
+    let command = generate_governor_command(
+        &upgrade,
+        &governor,
+        &entrypoint,
+        &governor.propose_fn,
+        governor.delay,
+    )?;
+    println!("Executing: {}", command);
+    execute_command(&command)?;
+
+    println!(
+        "\n✅ Upgrade of {} proposed to governor {} (salt {})",
+        contract_id::display_contract(&upgrade),
+        governor.governor,
+        operation_salt(&upgrade, &encode_payload(&upgrade, &entrypoint)?)
+    );
+    println!(
+        "   Once the delay has elapsed, run `stellar upgrader execute` with the same arguments"
+    );
+    Ok(())
+}
+
+/// Run the execute command, executing the proposed upgrade once the checks pass again
+pub fn run_execute(args: &ExecuteArgs) -> Result<(), String> {
+    let (governor, upgrade, entrypoint, checks) =
+        prepare_governed_upgrade(&args.governor, &args.upgrade)?;
+    if upgrade.wasm.is_some() {
+        wasm::ensure_installed(&upgrade)?;
+    }
+
+    // The governor refuses operations that weren't proposed or whose delay hasn't elapsed
+    let simulation = UpgradeArgs {
+        build_only: false,
+        send: Some("no".to_string()),
+        ..upgrade.clone()
+    };
+    let command = generate_governor_command(
+        &simulation,
+        &governor,
+        &entrypoint,
+        &governor.execute_fn,
+        None,
+    )?;
+    capture_command(&command).map_err(|e| {
+        format!(
+            "❌ The governor refused to execute the upgrade, it may not be proposed or its delay may not have elapsed yet: {}",
+            e.trim()
+        )
+    })?;
+
+    let command =
+        generate_governor_command(&upgrade, &governor, &entrypoint, &governor.execute_fn, None)?;
+    println!("Executing: {}", command);
+    let entry = is_submitted(&upgrade).then(|| HistoryEntry::fetch(&upgrade, &checks));
+    let output = execute_command(&command)?;
+    if let Some(entry) = entry {
+        history::record(entry, &output);
+    }
+
+    if verification::should_verify(&upgrade) {
+        verification::verify_upgrade(&upgrade)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    const GOVERNOR: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+    const OPERATOR: &str = "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54";
+    const HASH: &str = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+
+    fn create_test_args() -> ProposeArgs {
+        ProposeArgs::try_parse_from([
+            "propose",
+            "--governor",
+            GOVERNOR,
+            "--id",
+            "CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB",
+            "--wasm-hash",
+            HASH,
+        ])
+        .unwrap()
+    }
+
+    fn entrypoint(interface: &str) -> UpgradeEntrypoint {
+        contract_info::find_upgrade_entrypoint(
+            &contract_info::parse_functions(interface),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn plain_entrypoint() -> UpgradeEntrypoint {
+        entrypoint("fn upgrade(env: soroban_sdk::Env, new_wasm_hash: soroban_sdk::BytesN<32>);")
+    }
+
+    #[test]
+    fn test_operation_salt() {
+        let args = create_test_args().upgrade;
+        let payload = encode_payload(&args, &plain_entrypoint()).unwrap();
+        assert_eq!(operation_salt(&args, &payload).len(), 64);
+        assert_eq!(
+            operation_salt(&args, &payload),
+            operation_salt(&args.clone(), &payload)
+        );
+
+        let mut other = args.clone();
+        other.wasm_hash = "1".repeat(64);
+        assert_ne!(
+            operation_salt(&args, &payload),
+            operation_salt(&other, &payload)
+        );
+
+        // Different upgrade arguments are different operations
+        assert_ne!(
+            operation_salt(&args, &payload),
+            operation_salt(&args, r#"[{"u32":2}]"#)
+        );
+    }
+
+    #[test]
+    fn test_encode_payload() {
+        let mut args = create_test_args().upgrade;
+        let entrypoint = entrypoint(
+            "fn upgrade(env: soroban_sdk::Env, operator: soroban_sdk::Address, new_wasm_hash: soroban_sdk::BytesN<32>, version: u32);",
+        );
+
+        // Arguments follow the declaration order, whatever order they were given in
+        args.upgrade_args = vec![
+            ("version".to_string(), r#"{"u32":2}"#.to_string()),
+            ("operator".to_string(), OPERATOR.to_string()),
+        ];
+        assert_eq!(
+            encode_payload(&args, &entrypoint).unwrap(),
+            format!(
+                r#"[{{"address":"{}"}},{{"bytes":"{}"}},{{"u32":2}}]"#,
+                OPERATOR, HASH
+            )
+        );
+
+        args.upgrade_args = vec![
+            ("version".to_string(), "2".to_string()),
+            ("operator".to_string(), OPERATOR.to_string()),
+        ];
+        assert!(encode_payload(&args, &entrypoint)
+            .unwrap_err()
+            .contains("ScVal JSON"));
+
+        args.upgrade_args = vec![("operator".to_string(), OPERATOR.to_string())];
+        assert!(encode_payload(&args, &entrypoint)
+            .unwrap_err()
+            .contains("`version`"));
+
+        args.upgrade_args = vec![("admin".to_string(), OPERATOR.to_string())];
+        assert!(encode_payload(&args, &entrypoint)
+            .unwrap_err()
+            .contains("no parameter `admin`"));
+
+        args.upgrade_args = vec![];
+        args.contract_args = vec!["--operator".to_string(), OPERATOR.to_string()];
+        assert!(encode_payload(&args, &plain_entrypoint()).is_err());
+    }
+
+    #[test]
+    fn test_generate_governor_command() {
+        let mut args = create_test_args();
+        args.governor.governor_args = vec![("proposer".to_string(), OPERATOR.to_string())];
+        let entrypoint = plain_entrypoint();
+        let payload = format!(r#"[{{"bytes":"{}"}}]"#, HASH);
+
+        let command = generate_governor_command(
+            &args.upgrade,
+            &args.governor,
+            &entrypoint,
+            &args.governor.propose_fn,
+            Some(17280),
+        )
+        .unwrap();
+        assert!(command.starts_with(&format!("stellar contract invoke --id {}", GOVERNOR)));
+        assert!(command.contains(&format!(
+            " -- schedule --target CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB --function upgrade --args '{}' --salt {}",
+            payload,
+            operation_salt(&args.upgrade, &payload)
+        )));
+        assert!(command.ends_with(&format!(" --delay 17280 --proposer {}", OPERATOR)));
+
+        let command =
+            generate_governor_command(&args.upgrade, &args.governor, &entrypoint, "execute", None)
+                .unwrap();
+        assert!(command.contains(" -- execute --target "));
+        assert!(!command.contains("--delay"));
+    }
+
+    #[test]
+    fn test_governed_upgrade_refuses_force() {
+        let mut args = create_test_args();
+        args.upgrade.force = true;
+
+        let err = prepare_governed_upgrade(&args.governor, &args.upgrade).unwrap_err();
+        assert!(err.contains("--force"));
+    }
+}
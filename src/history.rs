@@ -0,0 +1,473 @@
+use crate::config::CONFIG_FILE;
+use crate::ledger;
+use crate::security_checks::version_check::VersionCheck;
+use crate::{HistoryArgs, UpgradeArgs};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory of the project history, next to the configuration file
+pub const PROJECT_DIR: &str = ".stellar-upgrader";
+
+/// JSON Lines file the history is appended to
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// A performed upgrade, as recorded in the history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// UTC time of the upgrade, RFC 3339
+    pub timestamp: String,
+    /// Source account that submitted the upgrade
+    pub operator: String,
+    pub network: String,
+    pub contract_id: String,
+    #[serde(default)]
+    pub alias: Option<String>,
+    pub old_hash: Option<String>,
+    pub new_hash: String,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    /// Security checks that passed, none when skipped with --force
+    pub checks: Vec<String>,
+    pub forced: bool,
+    #[serde(default)]
+    pub rollback: bool,
+    pub transaction_hash: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        args: &UpgradeArgs,
+        checks: &[String],
+        old_hash: Option<String>,
+        old_version: Option<String>,
+        new_version: Option<String>,
+    ) -> Self {
+        HistoryEntry {
+            timestamp: format_timestamp(now()),
+            operator: args.source.clone(),
+            network: args.network.clone(),
+            contract_id: args.id.clone(),
+            alias: args.id_alias.clone(),
+            old_hash,
+            new_hash: args.wasm_hash.clone(),
+            old_version,
+            new_version,
+            checks: checks.to_vec(),
+            forced: args.force,
+            rollback: args.rollback,
+            transaction_hash: None,
+        }
+    }
+
+    /// Entry of the upgrade, with the state of the contract before it
+    ///
+    /// The history must not get in the way of the upgrade, so lookup failures leave fields empty.
+    pub fn fetch(args: &UpgradeArgs, checks: &[String]) -> Self {
+        let version_check = VersionCheck::new();
+        let version = |wasm_hash: Option<&str>| {
+            version_check
+                .get_contract_metadata(args, wasm_hash)
+                .and_then(|metadata| version_check.extract_binver(&metadata))
+                .ok()
+        };
+
+        HistoryEntry::new(
+            args,
+            checks,
+            ledger::fetch_contract_wasm_hash(&args.id, &crate::network_flags(args)).ok(),
+            version(None),
+            version(Some(&args.wasm_hash)),
+        )
+    }
+
+    /// Whether the entry matches the filters of the history command
+    pub fn matches(&self, args: &HistoryArgs) -> bool {
+        let id_matches = args
+            .id
+            .as_ref()
+            .is_none_or(|id| self.contract_id == *id || self.alias.as_deref() == Some(id.as_str()));
+        let network_matches = args
+            .network
+            .as_ref()
+            .is_none_or(|network| self.network == *network);
+
+        id_matches && network_matches
+    }
+
+    /// Print the entry on a few lines
+    pub fn print(&self) {
+        let contract = match &self.alias {
+            Some(alias) => format!("{} ({})", alias, self.contract_id),
+            None => self.contract_id.clone(),
+        };
+        let unknown = "?".to_string();
+        let kind = if self.rollback { "rollback" } else { "upgrade" };
+
+        println!(
+            "{}  {} of {} on {} by {}",
+            self.timestamp, kind, contract, self.network, self.operator
+        );
+        println!(
+            "  version {} -> {}, WASM {} -> {}",
+            self.old_version.as_ref().unwrap_or(&unknown),
+            self.new_version.as_ref().unwrap_or(&unknown),
+            self.old_hash.as_ref().unwrap_or(&unknown),
+            self.new_hash
+        );
+        if self.forced {
+            println!("  ⚠️  security checks skipped with --force");
+        } else {
+            println!("  checks: {}", self.checks.join(", "));
+        }
+        if let Some(hash) = &self.transaction_hash {
+            println!("  transaction: {}", hash);
+        }
+    }
+}
+
+/// Current Unix timestamp
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format a Unix timestamp as an RFC 3339 UTC date and time
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let secs_of_day = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Extract the transaction hash the Stellar CLI logs when submitting a transaction
+pub fn extract_transaction_hash(output: &str) -> Option<String> {
+    let hash = Regex::new(r"(?i)transaction hash[^0-9a-f]*([0-9a-f]{64})").unwrap();
+    hash.captures(output)
+        .map(|captures| captures[1].to_lowercase())
+}
+
+/// Location of the history: in the project when it has a configuration file, in the XDG data
+/// directory otherwise
+pub fn resolve_history_path(
+    project_dir: &Path,
+    xdg_data_home: Option<&str>,
+    home: Option<&str>,
+) -> Result<PathBuf, String> {
+    if project_dir.join(CONFIG_FILE).exists() {
+        return Ok(project_dir.join(PROJECT_DIR).join(HISTORY_FILE));
+    }
+
+    let data_dir = match (xdg_data_home.filter(|dir| !dir.is_empty()), home) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(home)) => Path::new(home).join(".local").join("share"),
+        (None, None) => {
+            return Err(
+                "Can't locate the upgrade history: neither XDG_DATA_HOME nor HOME is set"
+                    .to_string(),
+            )
+        }
+    };
+    Ok(data_dir.join("stellar-upgrader").join(HISTORY_FILE))
+}
+
+/// Location of the history for the current directory
+pub fn history_path() -> Result<PathBuf, String> {
+    resolve_history_path(
+        Path::new("."),
+        std::env::var("XDG_DATA_HOME").ok().as_deref(),
+        std::env::var("HOME").ok().as_deref(),
+    )
+}
+
+/// Append an entry to the history file
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let line = serde_json::to_string(entry)
+        .map_err(|e| format!("Failed to serialize the history entry: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Read the entries of the history file, oldest first
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                format!(
+                    "Invalid history entry at {}:{}: {}",
+                    path.display(),
+                    index + 1,
+                    e
+                )
+            })
+        })
+        .collect()
+}
+
+/// Record a performed upgrade, warning instead of failing since the upgrade already happened
+pub fn record(mut entry: HistoryEntry, command_output: &str) {
+    entry.transaction_hash = extract_transaction_hash(command_output);
+
+    match history_path().and_then(|path| append(&path, &entry).map(|_| path)) {
+        Ok(path) => println!("✅ Upgrade recorded in {}", path.display()),
+        Err(e) => println!("⚠️  Failed to record the upgrade in the history: {}", e),
+    }
+}
+
+/// WASM hash replaced by the last recorded upgrade to the current WASM
+pub fn previous_wasm_hash(
+    entries: &[HistoryEntry],
+    contract_id: &str,
+    network: &str,
+    current_hash: &str,
+) -> Option<String> {
+    entries
+        .iter()
+        .rev()
+        .find(|entry| {
+            entry.contract_id == contract_id
+                && entry.network == network
+                && entry.new_hash == current_hash
+        })
+        .and_then(|entry| entry.old_hash.clone())
+}
+
+/// Most recent recorded upgrade of the contract
+pub fn last_upgrade(
+    entries: &[HistoryEntry],
+    contract_id: &str,
+    network: &str,
+) -> Option<HistoryEntry> {
+    entries
+        .iter()
+        .rev()
+        .find(|entry| entry.contract_id == contract_id && entry.network == network)
+        .cloned()
+}
+
+/// Run the history command
+pub fn run_history(args: &HistoryArgs) -> Result<(), String> {
+    let path = history_path()?;
+    let entries = read(&path)?;
+
+    let mut matching: Vec<&HistoryEntry> = entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.matches(args))
+        .collect();
+    if let Some(limit) = args.limit {
+        matching.truncate(limit);
+    }
+
+    if args.json {
+        for entry in matching {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| format!("Failed to serialize the history entry: {}", e))?;
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    if matching.is_empty() {
+        println!("No upgrades recorded in {}", path.display());
+        return Ok(());
+    }
+    for entry in matching {
+        entry.print();
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACT: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+    const OLD_HASH: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    const NEW_HASH: &str = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: CONTRACT.to_string(),
+            wasm_hash: NEW_HASH.to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    fn create_test_entry() -> HistoryEntry {
+        HistoryEntry::new(
+            &create_test_args(),
+            &["Version Check".to_string()],
+            Some(OLD_HASH.to_string()),
+            Some("1.0.0".to_string()),
+            Some("1.1.0".to_string()),
+        )
+    }
+
+    fn history_args() -> HistoryArgs {
+        HistoryArgs {
+            id: None,
+            network: None,
+            limit: None,
+            json: false,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("stellar-upgrader-{}-{}", name, std::process::id()))
+            .join(HISTORY_FILE)
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_extract_transaction_hash() {
+        let output = format!("ℹ️  Signing transaction: {}\n", NEW_HASH);
+        assert_eq!(extract_transaction_hash(&output), None);
+
+        let output = format!("ℹ️  Transaction hash is {}\n", NEW_HASH.to_uppercase());
+        assert_eq!(
+            extract_transaction_hash(&output),
+            Some(NEW_HASH.to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_history_path() {
+        let project = Path::new("/nonexistent");
+        assert_eq!(
+            resolve_history_path(project, Some("/data"), Some("/home/alice")).unwrap(),
+            PathBuf::from("/data/stellar-upgrader/history.jsonl")
+        );
+        assert_eq!(
+            resolve_history_path(project, None, Some("/home/alice")).unwrap(),
+            PathBuf::from("/home/alice/.local/share/stellar-upgrader/history.jsonl")
+        );
+        assert!(resolve_history_path(project, None, None).is_err());
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let path = temp_path("history");
+        let _ = std::fs::remove_file(&path);
+        assert!(read(&path).unwrap().is_empty());
+
+        let first = create_test_entry();
+        let mut second = create_test_entry();
+        second.transaction_hash = Some(OLD_HASH.to_string());
+        append(&path, &first).unwrap();
+        append(&path, &second).unwrap();
+
+        assert_eq!(read(&path).unwrap(), vec![first, second]);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_read_invalid_entry() {
+        let path = temp_path("invalid");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "{}\n").unwrap();
+
+        assert!(read(&path).unwrap_err().contains("history.jsonl:1"));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut entry = create_test_entry();
+        entry.alias = Some("token".to_string());
+
+        let mut args = history_args();
+        assert!(entry.matches(&args));
+
+        args.id = Some("token".to_string());
+        assert!(entry.matches(&args));
+        args.id = Some(CONTRACT.to_string());
+        assert!(entry.matches(&args));
+
+        args.network = Some("mainnet".to_string());
+        assert!(!entry.matches(&args));
+    }
+
+    #[test]
+    fn test_previous_wasm_hash() {
+        let upgrade = create_test_entry();
+        let mut rollback = create_test_entry();
+        rollback.old_hash = Some(NEW_HASH.to_string());
+        rollback.new_hash = OLD_HASH.to_string();
+        let entries = vec![upgrade, rollback];
+
+        assert_eq!(
+            previous_wasm_hash(&entries, CONTRACT, "testnet", NEW_HASH),
+            Some(OLD_HASH.to_string())
+        );
+        assert_eq!(
+            previous_wasm_hash(&entries, CONTRACT, "testnet", OLD_HASH),
+            Some(NEW_HASH.to_string())
+        );
+        assert_eq!(
+            previous_wasm_hash(&entries, CONTRACT, "mainnet", NEW_HASH),
+            None
+        );
+    }
+
+    #[test]
+    fn test_last_upgrade() {
+        let first = create_test_entry();
+        let mut second = create_test_entry();
+        second.new_hash = OLD_HASH.to_string();
+        let entries = vec![first, second.clone()];
+
+        assert_eq!(last_upgrade(&entries, CONTRACT, "testnet"), Some(second));
+        assert_eq!(last_upgrade(&entries, CONTRACT, "mainnet"), None);
+    }
+}
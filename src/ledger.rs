@@ -0,0 +1,194 @@
+use crate::capture_command;
+use crate::transaction::as_u64;
+use regex::Regex;
+use serde_json::Value;
+
+/// Base64 XDR of `ScVal::LedgerKeyContractInstance`, the storage key of a contract instance
+const CONTRACT_INSTANCE_KEY_XDR: &str = "AAAAFA==";
+
+/// Fetch the ledger entry of a contract instance as JSON
+///
+/// `network_flags` are the network options of the lookup, see [`crate::network_flags`].
+pub fn fetch_contract_instance(contract_id: &str, network_flags: &str) -> Result<String, String> {
+    fetch_contract_data(
+        contract_id,
+        CONTRACT_INSTANCE_KEY_XDR,
+        "persistent",
+        network_flags,
+    )
+    .map_err(|e| format!("Failed to fetch contract instance: {}", e))
+}
+
+/// Fetch a contract data ledger entry as JSON, by the base64 XDR of its `ScVal` key
+pub fn fetch_contract_data(
+    contract_id: &str,
+    key_xdr: &str,
+    durability: &str,
+    network_flags: &str,
+) -> Result<String, String> {
+    let command = format!(
+        "stellar ledger entry fetch contract-data --contract {} --key-xdr {} --durability {} {} --output json",
+        contract_id, key_xdr, durability, network_flags
+    );
+
+    capture_command(&command)
+}
+
+/// Fetch the ledger entry of an account as JSON
+pub fn fetch_account(account_id: &str, network_flags: &str) -> Result<String, String> {
+    let command = format!(
+        "stellar ledger entry fetch account --account {} {} --output json",
+        account_id, network_flags
+    );
+
+    capture_command(&command).map_err(|e| format!("Failed to fetch account {}: {}", account_id, e))
+}
+
+/// Fetch the ledger entry of an uploaded WASM as JSON
+pub fn fetch_contract_code(wasm_hash: &str, network_flags: &str) -> Result<String, String> {
+    let command = format!(
+        "stellar ledger entry fetch contract-code --wasm-hash {} {} --output json",
+        wasm_hash, network_flags
+    );
+
+    capture_command(&command).map_err(|e| format!("Failed to fetch WASM code entry: {}", e))
+}
+
+/// Fetch the WASM hash the contract instance currently points to
+pub fn fetch_contract_wasm_hash(contract_id: &str, network_flags: &str) -> Result<String, String> {
+    let instance = fetch_contract_instance(contract_id, network_flags)?;
+    extract_executable_hash(&instance)
+}
+
+/// Extract the executable WASM hash from a contract instance ledger entry JSON
+pub fn extract_executable_hash(instance_json: &str) -> Result<String, String> {
+    // The executable is encoded as: "executable":{"wasm":"9ab3011a..."}
+    let executable = Regex::new(r#""wasm"\s*:\s*"([0-9a-fA-F]{64})""#).unwrap();
+    match executable.captures(instance_json) {
+        Some(captures) => Ok(captures[1].to_lowercase()),
+        None => Err("Contract instance does not point to a WASM executable".to_string()),
+    }
+}
+
+/// Extract the latest ledger the RPC knew of from a ledger entry response JSON
+pub fn extract_latest_ledger(entries_json: &str) -> Result<u64, String> {
+    let json: Value = serde_json::from_str(entries_json)
+        .map_err(|e| format!("Invalid ledger entry JSON: {}", e))?;
+
+    as_u64(&json["latest_ledger"])
+        .ok_or_else(|| "Latest ledger missing from the ledger entry response".to_string())
+}
+
+/// A contract storage entry, with its `ScVal` key and value as JSON
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractDataEntry {
+    /// "instance", "persistent" or "temporary"
+    pub durability: String,
+    pub key: Value,
+    pub val: Value,
+    /// Last ledger the entry is live until
+    pub live_until: Option<u64>,
+}
+
+fn parse_entries(entries_json: &str) -> Result<Vec<Value>, String> {
+    let json: Value = serde_json::from_str(entries_json)
+        .map_err(|e| format!("Invalid ledger entry JSON: {}", e))?;
+
+    Ok(json["entries"].as_array().cloned().unwrap_or_default())
+}
+
+/// Extract the instance storage entries from a contract instance ledger entry JSON
+pub fn parse_instance_storage(instance_json: &str) -> Result<Vec<ContractDataEntry>, String> {
+    let entries = parse_entries(instance_json)?;
+    let entry = entries
+        .first()
+        .ok_or_else(|| "Contract instance not found".to_string())?;
+    let live_until = as_u64(&entry["live_until_ledger_seq"]);
+
+    let storage = entry["val"]["contract_data"]["val"]["contract_instance"]["storage"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+
+    Ok(storage
+        .into_iter()
+        .map(|item| ContractDataEntry {
+            durability: "instance".to_string(),
+            key: item["key"].clone(),
+            val: item["val"].clone(),
+            live_until,
+        })
+        .collect())
+}
+
+/// Extract the contract data entry from a `stellar ledger entry fetch contract-data` JSON
+pub fn parse_contract_data(entry_json: &str) -> Result<Option<ContractDataEntry>, String> {
+    let entries = parse_entries(entry_json)?;
+
+    Ok(entries.first().map(|entry| {
+        let data = &entry["val"]["contract_data"];
+        ContractDataEntry {
+            durability: data["durability"].as_str().unwrap_or_default().to_string(),
+            key: data["key"].clone(),
+            val: data["val"].clone(),
+            live_until: as_u64(&entry["live_until_ledger_seq"]),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_executable_hash() {
+        let instance = r#"{"latest_ledger":1000,"entries":[{"key":{"contract_data":{"contract":"CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX","key":"ledger_key_contract_instance","durability":"persistent"}},"val":{"contract_data":{"ext":"v0","contract":"CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX","key":"ledger_key_contract_instance","durability":"persistent","val":{"contract_instance":{"executable":{"wasm":"9AB3011A533A116F82F99EBCD00E72CDCA5E42159AACA379FD249FDBD982D9FF"},"storage":null}}}},"last_modified_ledger":900,"live_until_ledger_seq":5000}]}"#;
+
+        let hash = extract_executable_hash(instance).unwrap();
+        assert_eq!(
+            hash,
+            "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"
+        );
+    }
+
+    #[test]
+    fn test_extract_latest_ledger() {
+        assert_eq!(
+            extract_latest_ledger(r#"{"latest_ledger":1000,"entries":[]}"#).unwrap(),
+            1000
+        );
+        assert!(extract_latest_ledger(r#"{"entries":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_instance_storage() {
+        let instance = r#"{"entries":[{"val":{"contract_data":{"val":{"contract_instance":{"executable":{"wasm":"9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"},"storage":[{"key":{"vec":[{"symbol":"Admin"}]},"val":{"address":"GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54"}}]}}}},"live_until_ledger_seq":5000}]}"#;
+
+        let storage = parse_instance_storage(instance).unwrap();
+        assert_eq!(storage.len(), 1);
+        assert_eq!(storage[0].durability, "instance");
+        assert_eq!(storage[0].key["vec"][0]["symbol"], "Admin");
+        assert_eq!(storage[0].live_until, Some(5000));
+
+        assert!(parse_instance_storage(r#"{"entries":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_contract_data() {
+        let entry = r#"{"entries":[{"val":{"contract_data":{"durability":"persistent","key":{"vec":[{"symbol":"Counter"}]},"val":{"u32":7}}},"live_until_ledger_seq":"12000"}]}"#;
+
+        let data = parse_contract_data(entry).unwrap().unwrap();
+        assert_eq!(data.durability, "persistent");
+        assert_eq!(data.val["u32"], 7);
+        assert_eq!(data.live_until, Some(12000));
+
+        assert_eq!(parse_contract_data(r#"{"entries":[]}"#).unwrap(), None);
+    }
+
+    #[test]
+    fn test_extract_executable_hash_stellar_asset() {
+        let instance = r#"{"entries":[{"val":{"contract_data":{"val":{"contract_instance":{"executable":"stellar_asset","storage":null}}}}}]}"#;
+
+        assert!(extract_executable_hash(instance).is_err());
+    }
+}
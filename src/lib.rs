@@ -1,7 +1,29 @@
 use clap::{Parser, Subcommand};
+use security_checks::contract_info::{self, FunctionParam};
+use serde::{Deserialize, Serialize};
+use std::io::IsTerminal;
 use std::process::Command;
 
+mod authorization;
+mod batch;
+mod config;
+mod contract_id;
+mod describe;
+mod governor;
+mod history;
+mod ledger;
+mod migration;
+mod multisig;
+mod offline;
+mod plan;
+mod rollback;
 mod security_checks;
+mod simulation;
+mod status;
+mod transaction;
+mod ttl;
+mod verification;
+mod wasm;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -11,21 +33,263 @@ pub struct UpgraderCli {
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Upgrade a Stellar smart contract
     Upgrade(UpgradeArgs),
+    /// Upgrade the contracts listed in a manifest file
+    UpgradeBatch(BatchArgs),
+    /// Check and simulate an upgrade, writing the plan to apply
+    Plan(PlanArgs),
+    /// Apply an upgrade plan, if the contract didn't change since planning
+    Apply(ApplyArgs),
+    /// Roll a contract back to the WASM it ran before its last upgrade
+    Rollback(RollbackArgs),
+    /// List the upgrades recorded in the history
+    History(HistoryArgs),
+    /// Report the current upgrade state of a contract
+    Status(StatusArgs),
+    /// Check and simulate an upgrade, writing the unsigned transaction to sign offline
+    Build(BuildArgs),
+    /// Sign an upgrade transaction file, without network access for well-known networks
+    Sign(SignArgs),
+    /// Submit a signed upgrade transaction file
+    Submit(SubmitArgs),
+    /// Decode an upgrade transaction envelope and confirm what it does
+    DescribeTx(DescribeTxArgs),
+    /// Check an upgrade and propose it to the governor contract controlling the contract
+    Propose(ProposeArgs),
+    /// Check a proposed upgrade again and execute it through the governor once its delay elapsed
+    Execute(ExecuteArgs),
 }
 
-#[derive(Parser)]
+#[derive(Parser, Clone, Debug)]
+pub struct PlanArgs {
+    /// File to write the plan to
+    #[arg(long, default_value = "plan.json")]
+    pub out: String,
+
+    #[command(flatten)]
+    pub upgrade: UpgradeArgs,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct ApplyArgs {
+    /// Plan file written by the plan command
+    pub plan: String,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct BuildArgs {
+    /// File to write the unsigned transaction to
+    #[arg(long, default_value = "upgrade-tx.json")]
+    pub out: String,
+
+    /// Ledger sequence, or Unix timestamp, before which the transaction can't be submitted
+    #[arg(long)]
+    pub not_before: Option<u64>,
+
+    #[command(flatten)]
+    pub upgrade: UpgradeArgs,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct SignArgs {
+    /// Transaction file written by the build command
+    pub file: String,
+
+    /// Identity or secret key to sign with
+    #[arg(long)]
+    pub sign_with_key: Option<String>,
+
+    /// Transaction file signed by another signer, whose signatures to add, can be repeated
+    #[arg(long = "add-signature")]
+    pub add_signatures: Vec<String>,
+
+    /// File to write the signed transaction to (defaults to signing in place)
+    #[arg(long)]
+    pub out: Option<String>,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct SubmitArgs {
+    /// Signed transaction file
+    pub file: String,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct GovernorArgs {
+    /// Governor or timelock contract ID or alias performing the upgrade
+    #[arg(long)]
+    pub governor: String,
+
+    /// Governor function scheduling the upgrade call
+    #[arg(long, default_value = "schedule")]
+    pub propose_fn: String,
+
+    /// Governor function executing the scheduled upgrade call
+    #[arg(long, default_value = "execute")]
+    pub execute_fn: String,
+
+    /// Delay to schedule the upgrade call with, when the governor takes one
+    #[arg(long)]
+    pub delay: Option<u32>,
+
+    /// Additional governor function argument as NAME=VALUE (e.g. proposer=G...)
+    #[arg(long = "governor-arg", value_parser = parse_key_value)]
+    pub governor_args: Vec<(String, String)>,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct ProposeArgs {
+    #[command(flatten)]
+    pub governor: GovernorArgs,
+
+    #[command(flatten)]
+    pub upgrade: UpgradeArgs,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct ExecuteArgs {
+    #[command(flatten)]
+    pub governor: GovernorArgs,
+
+    #[command(flatten)]
+    pub upgrade: UpgradeArgs,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct DescribeTxArgs {
+    /// Base64 transaction envelope, or a file containing it or written by the build command
+    pub xdr: String,
+
+    /// Contract ID or alias the transaction must upgrade
+    #[arg(long)]
+    pub id: Option<String>,
+
+    /// WASM hash the transaction must upgrade to
+    #[arg(long = "wasm-hash", value_parser = parse_wasm_hash)]
+    pub wasm_hash: Option<String>,
+
+    /// Name of the function the transaction must call
+    #[arg(long, default_value = "upgrade")]
+    pub upgrade_fn: Option<String>,
+
+    /// Network to use
+    #[arg(long, default_value = "testnet")]
+    pub network: String,
+
+    /// Run the security checks against the WASM hash found in the transaction
+    #[arg(long)]
+    pub check: bool,
+
+    /// Sandbox network (e.g. local) to probe whether the new code's upgrade function is protected
+    #[arg(long)]
+    pub sandbox_network: Option<String>,
+
+    /// Security check to skip, by name (e.g. unprotected-upgrade), can be repeated
+    #[arg(long = "skip-check")]
+    pub skip_checks: Vec<String>,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct RollbackArgs {
+    /// Contract ID or alias to roll back
+    #[arg(long)]
+    pub id: String,
+
+    /// WASM hash to restore (defaults to the WASM replaced by the last upgrade)
+    #[arg(long, value_parser = parse_wasm_hash)]
+    pub to: Option<String>,
+
+    /// Source account for the transaction
+    #[arg(long, default_value = "alice")]
+    pub source: String,
+
+    /// Network to use
+    #[arg(long, default_value = "testnet")]
+    pub network: String,
+
+    /// Additional upgrade arguments (e.g. --upgrade-fn, --force)
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct HistoryArgs {
+    /// Only list the upgrades of this contract ID or alias
+    #[arg(long)]
+    pub id: Option<String>,
+
+    /// Only list the upgrades on this network
+    #[arg(long)]
+    pub network: Option<String>,
+
+    /// Maximum number of upgrades to list, most recent first
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Print the entries as JSON Lines
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct StatusArgs {
+    /// Contract ID or alias
+    #[arg(long)]
+    pub id: String,
+
+    /// Network to use
+    #[arg(long, default_value = "testnet")]
+    pub network: String,
+
+    /// Name of the upgrade function (`upgrade` if omitted)
+    #[arg(long)]
+    pub upgrade_fn: Option<String>,
+
+    /// Name of the upgrade function parameter receiving the new WASM hash
+    #[arg(long)]
+    pub hash_arg: Option<String>,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct BatchArgs {
+    /// TOML or JSON manifest of the upgrades
+    #[arg(long)]
+    pub manifest: String,
+
+    /// Keep upgrading the other contracts when an upgrade fails its checks or execution
+    #[arg(long)]
+    pub continue_on_failure: bool,
+
+    /// Execute the upgrade plan without asking for confirmation
+    #[arg(long)]
+    pub yes: bool,
+}
+
+#[derive(Parser, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct UpgradeArgs {
-    /// The contract ID to upgrade
+    /// The contract ID or alias to upgrade
     #[arg(long)]
     pub id: String,
 
-    /// The new WASM hash for the upgrade
-    #[arg(long = "wasm-hash")]
+    /// Alias the contract ID was resolved from
+    #[arg(skip)]
+    pub id_alias: Option<String>,
+
+    /// Whether the upgrade restores an older WASM, allowing a version decrease
+    #[arg(skip)]
+    pub rollback: bool,
+
+    /// The new WASM hash for the upgrade, as 32 bytes of hex
+    #[arg(long = "wasm-hash", value_parser = parse_wasm_hash)]
     pub wasm_hash: String,
 
+    /// WASM file to upload first when --wasm-hash is not installed on the network
+    #[arg(long)]
+    pub wasm: Option<String>,
+
     /// Source account to pay for the upgrade
     #[arg(long, default_value = "alice")]
     pub source: String,
@@ -74,13 +338,172 @@ pub struct UpgradeArgs {
     #[arg(long)]
     pub force: bool,
 
+    /// Name of the upgrade function (`upgrade` if omitted)
+    #[arg(long)]
+    pub upgrade_fn: Option<String>,
+
+    /// Name of the upgrade function parameter receiving the new WASM hash
+    #[arg(long)]
+    pub hash_arg: Option<String>,
+
+    /// Additional upgrade function argument as NAME=VALUE (e.g. operator=G...)
+    #[arg(long = "upgrade-arg", value_parser = parse_key_value)]
+    pub upgrade_args: Vec<(String, String)>,
+
+    /// Acknowledge that a new or changed constructor won't run on upgrade
+    #[arg(long)]
+    pub ack_constructor: bool,
+
+    /// Name of a contract type enum used as storage keys (default: DataKey), can be repeated
+    #[arg(long = "storage-enum")]
+    pub storage_enums: Vec<String>,
+
+    /// Snapshot the live storage and decode it with the new code's types (always done on mainnet)
+    #[arg(long)]
+    pub snapshot: bool,
+
+    /// Base64 XDR of a persistent storage key to include in the snapshot, can be repeated
+    #[arg(long = "snapshot-key")]
+    pub snapshot_keys: Vec<String>,
+
+    /// Minimum number of ledgers the contract instance and new WASM must stay live for
+    #[arg(long, default_value_t = ttl::DEFAULT_MIN_TTL)]
+    pub min_ttl: u32,
+
+    /// Extend the contract instance and new WASM below --min-ttl before upgrading
+    #[arg(long)]
+    pub extend_ttl: bool,
+
+    /// Migration function to invoke on the new code after the upgrade
+    #[arg(long)]
+    pub migrate: Option<String>,
+
+    /// Migration function argument as NAME=VALUE, can be repeated
+    #[arg(long = "migrate-arg", value_parser = parse_key_value)]
+    pub migrate_args: Vec<(String, String)>,
+
+    /// Name of the entrypoint performing the upgrade and the migration in a single transaction
+    #[arg(long)]
+    pub atomic_fn: Option<String>,
+
+    /// Router contract exposing the atomic upgrade and migration entrypoint
+    #[arg(long)]
+    pub router: Option<String>,
+
+    /// Allow upgrading and migrating in two separate transactions when no atomic entrypoint exists
+    #[arg(long)]
+    pub allow_two_step: bool,
+
+    /// Sandbox network (e.g. local) to probe whether the new code's upgrade function is protected, required unless --force
+    #[arg(long)]
+    pub sandbox_network: Option<String>,
+
+    /// Constructor argument of the sandbox deployment as NAME=VALUE, can be repeated
+    #[arg(long = "probe-constructor-arg", value_parser = parse_key_value)]
+    pub probe_constructor_args: Vec<(String, String)>,
+
+    /// Security check to skip, by name (e.g. unprotected-upgrade), can be repeated
+    #[arg(long = "skip-check")]
+    pub skip_checks: Vec<String>,
+
+    /// Read-only invocation to simulate after the upgrade (e.g. "version" or "balance --id G...")
+    #[arg(long = "verify-call")]
+    pub verify_calls: Vec<String>,
+
+    /// Skip the pre-flight simulation of the upgrade transaction
+    #[arg(long)]
+    pub skip_simulation: bool,
+
+    /// Skip the post-upgrade verification step
+    #[arg(long)]
+    pub no_verify: bool,
+
     /// Additional contract function arguments
     #[arg(last = true)]
     pub contract_args: Vec<String>,
 }
 
-/// Execute a shell command and return the result
-fn execute_command(command: &str) -> Result<(), String> {
+#[cfg(test)]
+impl UpgradeArgs {
+    /// Arguments upgrading a test contract on testnet, with every option left unset
+    pub(crate) fn for_test() -> Self {
+        UpgradeArgs {
+            id: "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM".to_string(),
+            id_alias: None,
+            rollback: false,
+            wasm_hash: "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"
+                .to_string(),
+            wasm: None,
+            source: "alice".to_string(),
+            network: "testnet".to_string(),
+            rpc_url: None,
+            rpc_header: None,
+            network_passphrase: None,
+            fee: 100,
+            is_view: false,
+            instructions: None,
+            build_only: false,
+            send: None,
+            cost: false,
+            force: false,
+            upgrade_fn: None,
+            hash_arg: None,
+            upgrade_args: vec![],
+            ack_constructor: false,
+            storage_enums: vec![],
+            snapshot: false,
+            snapshot_keys: vec![],
+            min_ttl: ttl::DEFAULT_MIN_TTL,
+            extend_ttl: false,
+            migrate: None,
+            migrate_args: vec![],
+            atomic_fn: None,
+            router: None,
+            allow_two_step: false,
+            sandbox_network: None,
+            probe_constructor_args: vec![],
+            skip_checks: vec![],
+            verify_calls: vec![],
+            skip_simulation: false,
+            no_verify: false,
+            contract_args: vec![],
+        }
+    }
+}
+
+/// Parse a NAME=VALUE argument
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got '{}'", s)),
+    }
+}
+
+/// Parse a 32-byte WASM hash given as hex, optionally prefixed with 0x, into lowercase hex
+fn parse_wasm_hash(s: &str) -> Result<String, String> {
+    let hex = s.trim();
+    let hex = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+
+    if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex character '{}' in WASM hash", c));
+    }
+    if hex.len() != 64 {
+        return Err(format!(
+            "expected 32 bytes (64 hex characters), got {} hex characters",
+            hex.len()
+        ));
+    }
+
+    Ok(hex.to_lowercase())
+}
+
+/// Execute a shell command, printing its stdout and returning its stderr
+///
+/// The Stellar CLI logs progress such as the transaction hash on stderr.
+pub(crate) fn execute_command(command: &str) -> Result<String, String> {
     let output = if cfg!(target_os = "windows") {
         Command::new("cmd").args(["/C", command]).output()
     } else {
@@ -96,7 +519,7 @@ fn execute_command(command: &str) -> Result<(), String> {
                         println!("{}", stdout);
                     }
                 }
-                Ok(())
+                Ok(String::from_utf8_lossy(&output.stderr).into_owned())
             } else {
                 // Print stderr in case of error
                 if let Ok(stderr) = String::from_utf8(output.stderr) {
@@ -110,35 +533,79 @@ fn execute_command(command: &str) -> Result<(), String> {
     }
 }
 
-/// Generate the actual upgrade command
-pub fn generate_upgrade_command(args: &UpgradeArgs) -> String {
-    let mut command = format!(
-        "stellar contract invoke \
-        --id {} \
-        --source {} \
-        --network {}",
-        args.id, args.source, args.network
-    );
+/// Execute a shell command and return its stdout
+pub(crate) fn capture_command(command: &str) -> Result<String, String> {
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    };
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                String::from_utf8(output.stdout)
+                    .map_err(|_| "Failed to parse command output".to_string())
+            } else if let Ok(stderr) = String::from_utf8(output.stderr) {
+                Err(stderr)
+            } else {
+                Err("Command failed with unknown error".to_string())
+            }
+        }
+        Err(e) => Err(format!("Failed to execute command: {}", e)),
+    }
+}
+
+/// Whether the upgrade transaction is actually submitted to the network
+pub(crate) fn is_submitted(args: &UpgradeArgs) -> bool {
+    !args.is_view && !args.build_only && args.send.as_deref() != Some("no")
+}
+
+/// Generate a `stellar contract invoke` command for a contract, without function
+/// Network options of a generated `stellar` command
+///
+/// Every command of an upgrade gets them, so lookups, simulations and the upgrade itself all
+/// reach the same RPC.
+pub(crate) fn network_flags(args: &UpgradeArgs) -> String {
+    let mut flags = format!("--network {}", args.network);
 
-    // Add optional parameters
     if let Some(rpc_url) = &args.rpc_url {
-        command.push_str(&format!(" --rpc-url {}", rpc_url));
+        flags.push_str(&format!(" --rpc-url {}", rpc_url));
     }
 
     if let Some(headers) = &args.rpc_header {
         for header in headers {
-            command.push_str(&format!(" --rpc-header {}", header));
+            flags.push_str(&format!(" --rpc-header '{}'", header));
         }
     }
 
     if let Some(passphrase) = &args.network_passphrase {
-        command.push_str(&format!(" --network-passphrase {}", passphrase));
+        flags.push_str(&format!(" --network-passphrase '{}'", passphrase));
     }
 
+    flags
+}
+
+fn generate_invoke_command(args: &UpgradeArgs, contract_id: &str) -> String {
+    let mut command = format!(
+        "stellar contract invoke --id {} --source {} {}",
+        contract_id,
+        args.source,
+        network_flags(args)
+    );
+
     if args.fee != 100 {
         command.push_str(&format!(" --fee {}", args.fee));
     }
 
+    command
+}
+
+/// Generate the actual upgrade command
+pub fn generate_upgrade_command(args: &UpgradeArgs) -> String {
+    // A router contract performs the upgrade on behalf of the contract
+    let mut command = generate_invoke_command(args, args.router.as_deref().unwrap_or(&args.id));
+
     if args.is_view {
         command.push_str(" --is-view");
     }
@@ -160,8 +627,19 @@ pub fn generate_upgrade_command(args: &UpgradeArgs) -> String {
     }
 
     // Add the contract function and args
-    command.push_str(" -- upgrade");
-    command.push_str(&format!(" --new_wasm_hash {}", args.wasm_hash));
+    command.push_str(&format!(
+        " -- {}",
+        args.upgrade_fn.as_deref().unwrap_or("upgrade")
+    ));
+    command.push_str(&format!(
+        " --{} {}",
+        args.hash_arg.as_deref().unwrap_or("new_wasm_hash"),
+        args.wasm_hash
+    ));
+
+    for (name, value) in &args.upgrade_args {
+        command.push_str(&format!(" --{} {}", name, value));
+    }
 
     // Add any additional contract args
     if !args.contract_args.is_empty() {
@@ -175,7 +653,12 @@ pub fn generate_upgrade_command(args: &UpgradeArgs) -> String {
 
 /// Ask for user confirmation when using --force flag
 fn confirm_force_upgrade() -> Result<bool, String> {
-    print!("Are you sure you want to proceed without security checks? (y/N): ");
+    prompt_confirmation("Are you sure you want to proceed without security checks?")
+}
+
+/// Print a prompt and read the user's answer from stdin
+fn read_user_input(prompt: &str) -> Result<String, String> {
+    print!("{}", prompt);
     std::io::Write::flush(&mut std::io::stdout())
         .map_err(|e| format!("Failed to flush stdout: {}", e))?;
 
@@ -184,7 +667,12 @@ fn confirm_force_upgrade() -> Result<bool, String> {
         .read_line(&mut input)
         .map_err(|e| format!("Failed to read user input: {}", e))?;
 
-    let input = input.trim().to_lowercase();
+    Ok(input.trim().to_string())
+}
+
+/// Ask the user a yes/no question, defaulting to no
+pub(crate) fn prompt_confirmation(question: &str) -> Result<bool, String> {
+    let input = read_user_input(&format!("{} (y/N): ", question))?.to_lowercase();
     Ok(input == "y" || input == "yes")
 }
 
@@ -199,15 +687,138 @@ fn check_force_confirmation(input: Option<&str>) -> Result<bool, String> {
     }
 }
 
+/// Ask the user for the value of an upgrade function argument
+fn prompt_upgrade_arg(param: &FunctionParam) -> Result<String, String> {
+    read_user_input(&format!(
+        "Value for upgrade argument `{}` ({}): ",
+        param.name, param.type_name
+    ))
+}
+
+/// Error listing the upgrade arguments that can't be prompted for
+fn missing_upgrade_args_error(params: &[FunctionParam]) -> String {
+    let flags: Vec<String> = params
+        .iter()
+        .map(|param| format!("--upgrade-arg {}=<{}>", param.name, param.type_name))
+        .collect();
+    format!(
+        "❌ The upgrade function requires arguments that weren't given, pass them with: {}",
+        flags.join(" ")
+    )
+}
+
+/// Resolve the upgrade entrypoint of the deployed contract and its required arguments
+///
+/// Missing arguments are prompted for when `interactive` and stdin is a terminal.
+fn resolve_upgrade_entrypoint(
+    args: &UpgradeArgs,
+    interactive: bool,
+) -> Result<UpgradeArgs, String> {
+    let interface = contract_info::fetch_deployed_interface(args)?;
+    let functions = contract_info::parse_functions(&interface);
+    let entrypoint = contract_info::find_upgrade_entrypoint(
+        &functions,
+        args.upgrade_fn.as_deref(),
+        args.hash_arg.as_deref(),
+    )
+    .map_err(|e| {
+        format!(
+            "Failed to find the upgrade function of the deployed contract: {}",
+            e
+        )
+    })?;
+
+    // Arguments may also have been passed verbatim after `--`
+    let missing: Vec<FunctionParam> = entrypoint
+        .missing_params(&args.upgrade_args)
+        .into_iter()
+        .filter(|param| !args.contract_args.contains(&format!("--{}", param.name)))
+        .collect();
+    let can_prompt = interactive && std::io::stdin().is_terminal();
+    if !missing.is_empty() && !can_prompt {
+        return Err(missing_upgrade_args_error(&missing));
+    }
+
+    let mut resolved = args.clone();
+    for param in missing {
+        let value = prompt_upgrade_arg(&param)?;
+        resolved.upgrade_args.push((param.name, value));
+    }
+    resolved.upgrade_fn = Some(entrypoint.function);
+    resolved.hash_arg = Some(entrypoint.hash_param);
+
+    Ok(resolved)
+}
+
 /// Run the upgrade command after security checks
 pub fn run_upgrade(args: &UpgradeArgs) -> Result<(), String> {
     run_upgrade_with_input(args, None)
 }
 
+/// Run the upgrade-batch command
+pub fn run_batch(args: &BatchArgs) -> Result<(), String> {
+    batch::run_batch(args, None)
+}
+
+/// Run the plan command
+pub fn run_plan(args: &PlanArgs) -> Result<(), String> {
+    plan::run_plan(args)
+}
+
+/// Run the apply command
+pub fn run_apply(args: &ApplyArgs) -> Result<(), String> {
+    plan::run_apply(args)
+}
+
+/// Run the rollback command
+pub fn run_rollback(args: &RollbackArgs) -> Result<(), String> {
+    rollback::run_rollback(args)
+}
+
+/// Run the history command
+pub fn run_history(args: &HistoryArgs) -> Result<(), String> {
+    history::run_history(args)
+}
+
+/// Run the status command
+pub fn run_status(args: &StatusArgs) -> Result<(), String> {
+    status::run_status(args)
+}
+
+/// Run the build command
+pub fn run_build(args: &BuildArgs) -> Result<(), String> {
+    offline::run_build(args)
+}
+
+/// Run the sign command
+pub fn run_sign(args: &SignArgs) -> Result<(), String> {
+    offline::run_sign(args)
+}
+
+/// Run the submit command
+pub fn run_submit(args: &SubmitArgs) -> Result<(), String> {
+    offline::run_submit(args)
+}
+
+/// Run the describe-tx command
+pub fn run_describe_tx(args: &DescribeTxArgs) -> Result<(), String> {
+    describe::run_describe_tx(args)
+}
+
+/// Run the propose command
+pub fn run_propose(args: &ProposeArgs) -> Result<(), String> {
+    governor::run_propose(args)
+}
+
+/// Run the execute command
+pub fn run_execute(args: &ExecuteArgs) -> Result<(), String> {
+    governor::run_execute(args)
+}
+
 /// Run the upgrade command with optional input (for testing)
 pub fn run_upgrade_with_input(args: &UpgradeArgs, force_input: Option<&str>) -> Result<(), String> {
     // Conditionally perform security checks based on --force flag
-    if args.force {
+    let (args, checks) = &if args.force {
         println!("⚠️  WARNING: Security checks are being skipped due to --force flag!");
         println!("⚠️  This may result in upgrade failures or loss of upgradeability.");
         println!("⚠️  Proceed with caution!\n");
@@ -216,9 +827,66 @@ pub fn run_upgrade_with_input(args: &UpgradeArgs, force_input: Option<&str>) ->
             return Err("Upgrade cancelled by user".to_string());
         }
         println!();
+
+        let args = contract_id::resolve_args(args)?;
+        contract_id::print_target(&args);
+        // The upgrade function and its arguments are still needed without the checks
+        let resolved = resolve_upgrade_entrypoint(&args, true)?;
+        // A router is only invoked through its atomic entrypoint, even without the checks
+        (migration::resolve_atomic_migration(&resolved)?, Vec::new())
     } else {
-        // Perform security checks using the modular system
-        security_checks::run_all_checks(args)?;
+        prepare_upgrade(args, true)?
+    };
+
+    execute_upgrade(args, checks)
+}
+
+/// Resolve the upgrade target and run the security checks
+///
+/// Returns the arguments to upgrade with, and the names of the checks that passed. Missing
+/// upgrade arguments are only prompted for when `interactive`.
+pub(crate) fn prepare_upgrade(
+    args: &UpgradeArgs,
+    interactive: bool,
+) -> Result<(UpgradeArgs, Vec<String>), String> {
+    // Make sure aliases and typos don't point the upgrade at the wrong contract
+    let args = contract_id::resolve_args(args)?;
+    contract_id::print_target(&args);
+
+    // Perform security checks using the modular system
+    let checks = security_checks::run_all_checks(&args)?;
+
+    // Find how the deployed contract expects to be upgraded
+    let resolved = resolve_upgrade_entrypoint(&args, interactive)?;
+
+    // Upgrade and migrate in a single transaction when possible
+    Ok((migration::resolve_atomic_migration(&resolved)?, checks))
+}
+
+/// Submit a prepared upgrade, then verify and migrate it
+pub(crate) fn execute_upgrade(args: &UpgradeArgs, checks: &[String]) -> Result<(), String> {
+    // The new WASM is only uploaded once the upgrade goes ahead, the checks read --wasm locally
+    if args.wasm.is_some() {
+        wasm::ensure_installed(args)?;
+        println!();
+    }
+
+    // Keep the instance and the new code from expiring soon after the upgrade
+    if ttl::should_extend(args) {
+        ttl::extend_ttls(args)?;
+        println!();
+    }
+
+    // Simulate the transaction before sending it
+    if simulation::should_simulate(args) {
+        let report = simulation::simulate_upgrade(args)?;
+        report.print();
+        println!();
+
+        // Make sure the source account can authorize the upgrade before broadcasting
+        let source_address = authorization::resolve_source_address(&args.source)?;
+        authorization::analyze_authorization(&report.auth, &source_address, args.force)?;
+        println!();
     }
 
     // Generate the upgrade command
@@ -227,8 +895,26 @@ pub fn run_upgrade_with_input(args: &UpgradeArgs, force_input: Option<&str>) ->
     // Display the command to be executed
     println!("Executing: {}", command);
 
+    // Record the state of the contract before it changes
+    let entry = is_submitted(args).then(|| history::HistoryEntry::fetch(args, checks));
+
     // Actually execute the command
-    execute_command(&command)
+    let output = execute_command(&command)?;
+    if let Some(entry) = entry {
+        history::record(entry, &output);
+    }
+
+    // Confirm the contract now runs the new code
+    if verification::should_verify(args) {
+        verification::verify_upgrade(args)?;
+    }
+
+    // Migrate the storage with the new code
+    if migration::should_migrate(args) {
+        migration::run_migration(args)?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -237,21 +923,10 @@ mod tests {
 
     fn create_test_args_with_force(force: bool) -> UpgradeArgs {
         UpgradeArgs {
-            id: "test_contract".to_string(),
             wasm_hash: "test_hash".to_string(),
-            source: "alice".to_string(),
-            network: "testnet".to_string(),
-            rpc_url: None,
-            rpc_header: None,
-            network_passphrase: None,
-            fee: 100,
-            is_view: false,
-            instructions: None,
             build_only: true, // Use build_only to avoid actually executing commands
-            send: None,
-            cost: false,
             force,
-            contract_args: vec![],
+            ..UpgradeArgs::for_test()
         }
     }
 
@@ -332,9 +1007,10 @@ mod tests {
         assert!(result.is_err());
         // Should fail during security checks, not during command execution
         let error_msg = result.unwrap_err();
-        // The test fails because of invalid wasm hash or contract interface issues
+        // The test fails because the WASM can't be looked up, or contract interface issues
         assert!(
-            error_msg.contains("Failed to get contract interface")
+            error_msg.contains("Failed to fetch WASM code entry")
+                || error_msg.contains("Failed to get contract interface")
                 || error_msg.contains("Contract interface information not available")
                 || error_msg.contains("invalid")
                 || error_msg.contains("Failed to execute command")
@@ -346,19 +1022,16 @@ mod tests {
         let args = UpgradeArgs {
             id: "test_contract".to_string(),
             wasm_hash: "abc123".to_string(),
-            source: "alice".to_string(),
-            network: "testnet".to_string(),
             rpc_url: Some("https://test.com".to_string()),
             rpc_header: Some(vec!["Auth: Bearer token".to_string()]),
-            network_passphrase: None,
             fee: 200,
             is_view: true,
             instructions: Some(50000),
-            build_only: false,
             send: Some("yes".to_string()),
             cost: true,
             force: false, // force flag shouldn't affect command generation
             contract_args: vec!["--extra".to_string(), "arg".to_string()],
+            ..UpgradeArgs::for_test()
         };
 
         let command = generate_upgrade_command(&args);
@@ -368,7 +1041,7 @@ mod tests {
         assert!(command.contains("--source alice"));
         assert!(command.contains("--network testnet"));
         assert!(command.contains("--rpc-url https://test.com"));
-        assert!(command.contains("--rpc-header Auth: Bearer token"));
+        assert!(command.contains("--rpc-header 'Auth: Bearer token'"));
         assert!(command.contains("--fee 200"));
         assert!(command.contains("--is-view"));
         assert!(command.contains("--instructions 50000"));
@@ -379,6 +1052,71 @@ mod tests {
         assert!(command.contains("--extra arg"));
     }
 
+    #[test]
+    fn test_generate_upgrade_command_custom_entrypoint() {
+        let mut args = create_test_args_with_force(false);
+        args.upgrade_fn = Some("upgrade_code".to_string());
+        args.hash_arg = Some("hash".to_string());
+        args.upgrade_args = vec![("operator".to_string(), "GABC".to_string())];
+
+        let command = generate_upgrade_command(&args);
+        assert!(command.ends_with("-- upgrade_code --hash test_hash --operator GABC"));
+    }
+
+    #[test]
+    fn test_parse_wasm_hash() {
+        let hash = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+
+        assert_eq!(parse_wasm_hash(hash).unwrap(), hash);
+        assert_eq!(parse_wasm_hash(&hash.to_uppercase()).unwrap(), hash);
+        assert_eq!(parse_wasm_hash(&format!("0x{}", hash)).unwrap(), hash);
+        assert_eq!(
+            parse_wasm_hash(&format!("0X{}", hash.to_uppercase())).unwrap(),
+            hash
+        );
+
+        assert!(parse_wasm_hash(&hash[..62])
+            .unwrap_err()
+            .contains("got 62 hex characters"));
+        assert!(parse_wasm_hash(&format!("{}00", hash))
+            .unwrap_err()
+            .contains("got 66 hex characters"));
+        assert!(parse_wasm_hash(&format!("{}g", &hash[..63]))
+            .unwrap_err()
+            .contains("invalid hex character 'g'"));
+    }
+
+    #[test]
+    fn test_parse_key_value() {
+        assert_eq!(
+            parse_key_value("operator=GABC").unwrap(),
+            ("operator".to_string(), "GABC".to_string())
+        );
+        assert_eq!(
+            parse_key_value("memo=a=b").unwrap(),
+            ("memo".to_string(), "a=b".to_string())
+        );
+        assert!(parse_key_value("operator").is_err());
+        assert!(parse_key_value("=GABC").is_err());
+    }
+
+    #[test]
+    fn test_missing_upgrade_args_error() {
+        let params = vec![
+            FunctionParam {
+                name: "operator".to_string(),
+                type_name: "Address".to_string(),
+            },
+            FunctionParam {
+                name: "version".to_string(),
+                type_name: "u32".to_string(),
+            },
+        ];
+
+        let error = missing_upgrade_args_error(&params);
+        assert!(error.contains("--upgrade-arg operator=<Address> --upgrade-arg version=<u32>"));
+    }
+
     #[test]
     fn test_check_force_confirmation_function() {
         // Test positive confirmations
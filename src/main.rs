@@ -1,5 +1,9 @@
 use clap::Parser;
-use stellar_upgrader_plugin::{run_upgrade, Commands, UpgraderCli};
+use stellar_upgrader_plugin::{
+    run_apply, run_batch, run_build, run_describe_tx, run_execute, run_history, run_plan,
+    run_propose, run_rollback, run_sign, run_status, run_submit, run_upgrade, Commands,
+    UpgraderCli,
+};
 
 fn main() {
     let cli = UpgraderCli::parse();
@@ -11,5 +15,77 @@ fn main() {
                 std::process::exit(1);
             }
         }
+        Commands::UpgradeBatch(args) => {
+            if let Err(err) = run_batch(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Plan(args) => {
+            if let Err(err) = run_plan(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Apply(args) => {
+            if let Err(err) = run_apply(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Rollback(args) => {
+            if let Err(err) = run_rollback(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::History(args) => {
+            if let Err(err) = run_history(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Status(args) => {
+            if let Err(err) = run_status(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Build(args) => {
+            if let Err(err) = run_build(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Sign(args) => {
+            if let Err(err) = run_sign(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Submit(args) => {
+            if let Err(err) = run_submit(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::DescribeTx(args) => {
+            if let Err(err) = run_describe_tx(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Propose(args) => {
+            if let Err(err) = run_propose(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Execute(args) => {
+            if let Err(err) = run_execute(&args) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
     }
 }
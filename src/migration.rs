@@ -0,0 +1,392 @@
+use crate::security_checks::contract_info::{self, ContractFunction};
+use crate::{capture_command, generate_invoke_command, is_submitted, UpgradeArgs};
+
+/// Conventional name of the entrypoint upgrading and migrating in a single transaction
+const DEFAULT_ATOMIC_FN: &str = "upgrade_and_migrate";
+
+/// Whether a migration must be invoked after the upgrade
+pub fn should_migrate(args: &UpgradeArgs) -> bool {
+    args.migrate.is_some() && is_submitted(args)
+}
+
+/// Generate the command invoking the migration function on the new code
+pub fn generate_migrate_command(args: &UpgradeArgs, function: &str) -> String {
+    let mut command = generate_invoke_command(args, &args.id);
+
+    command.push_str(&format!(" -- {}", function));
+    for (name, value) in &args.migrate_args {
+        command.push_str(&format!(" --{} {}", name, value));
+    }
+
+    command
+}
+
+/// Turn an upgrade followed by a migration into a single call of the atomic entrypoint
+///
+/// Returns `None` when `functions` has no atomic entrypoint. The arguments of the entrypoint
+/// are taken from `--migrate-arg` and `--upgrade-arg` by name. When calling a router, the
+/// upgraded contract and the migration function are passed to its `Address` and `Symbol`
+/// parameters if exactly one parameter of that type isn't given explicitly.
+pub fn plan_atomic_invocation(
+    args: &UpgradeArgs,
+    functions: &[ContractFunction],
+) -> Result<Option<UpgradeArgs>, String> {
+    let atomic_fn = args.atomic_fn.as_deref().unwrap_or(DEFAULT_ATOMIC_FN);
+    let migrate = args.migrate.as_deref().unwrap_or_default();
+
+    let function = match functions.iter().find(|function| function.name == atomic_fn) {
+        Some(function) => function,
+        None => return Ok(None),
+    };
+    let entrypoint = contract_info::find_upgrade_entrypoint(
+        std::slice::from_ref(function),
+        Some(atomic_fn),
+        None,
+    )
+    .map_err(|e| format!("Invalid atomic entrypoint `{}`: {}", atomic_fn, e))?;
+    if !args.contract_args.is_empty() {
+        return Err(format!(
+            "Arguments after `--` can't be passed to `{}`, pass them by name with --migrate-arg",
+            atomic_fn
+        ));
+    }
+
+    let provided = |name: &str| {
+        args.migrate_args
+            .iter()
+            .chain(&args.upgrade_args)
+            .find(|(arg, _)| arg == name)
+            .map(|(_, value)| value.clone())
+    };
+    // A router parameter is only filled in when it can't be mistaken for another one
+    let unprovided_of_type = |suffix: &str| {
+        entrypoint
+            .extra_params
+            .iter()
+            .filter(|param| param.type_name.ends_with(suffix) && provided(&param.name).is_none())
+            .map(|param| param.name.as_str())
+            .collect::<Vec<_>>()
+    };
+    let addresses = unprovided_of_type("Address");
+    let symbols = unprovided_of_type("Symbol");
+
+    let mut invocation_args = Vec::new();
+    for param in &entrypoint.extra_params {
+        let value = match provided(&param.name) {
+            Some(value) => value,
+            None if args.router.is_some() && addresses == [param.name.as_str()] => {
+                args.id.clone()
+            }
+            None if args.router.is_some() && symbols == [param.name.as_str()] => {
+                migrate.to_string()
+            }
+            None => {
+                return Err(format!(
+                    "Missing value for argument `{}` of `{}`, pass it by name with --migrate-arg {}=VALUE",
+                    param.name, atomic_fn, param.name
+                ))
+            }
+        };
+        invocation_args.push((param.name.clone(), value));
+    }
+
+    let mut atomic = args.clone();
+    atomic.upgrade_fn = Some(entrypoint.function);
+    atomic.hash_arg = Some(entrypoint.hash_param);
+    atomic.upgrade_args = invocation_args;
+    // The migration happens within the upgrade transaction
+    atomic.migrate = None;
+    atomic.migrate_args = vec![];
+
+    Ok(Some(atomic))
+}
+
+/// Describe the call of the atomic entrypoint with its resolved arguments
+pub fn describe_invocation(atomic: &UpgradeArgs) -> String {
+    let hash_param = atomic.hash_arg.as_deref().unwrap_or("new_wasm_hash");
+    let arguments: Vec<String> = std::iter::once(format!("{}: {}", hash_param, atomic.wasm_hash))
+        .chain(
+            atomic
+                .upgrade_args
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value)),
+        )
+        .collect();
+
+    format!(
+        "{}({})",
+        atomic.upgrade_fn.as_deref().unwrap_or(DEFAULT_ATOMIC_FN),
+        arguments.join(", ")
+    )
+}
+
+/// Upgrade and migrate atomically when the contract or router supports it
+pub fn resolve_atomic_migration(args: &UpgradeArgs) -> Result<UpgradeArgs, String> {
+    let migrate = match &args.migrate {
+        Some(migrate) => migrate,
+        // A plain upgrade call on the router would upgrade the router itself
+        None if args.router.is_some() => return Err(
+            "❌ --router only upgrades through the router's atomic entrypoint, it needs --migrate"
+                .to_string(),
+        ),
+        None => return Ok(args.clone()),
+    };
+    let atomic_fn = args.atomic_fn.as_deref().unwrap_or(DEFAULT_ATOMIC_FN);
+
+    // The atomic entrypoint lives on the router, or on the contract itself
+    let host = match &args.router {
+        Some(router) => UpgradeArgs {
+            id: router.clone(),
+            ..args.clone()
+        },
+        None => args.clone(),
+    };
+    let interface = contract_info::fetch_deployed_interface(&host)?;
+    let functions = contract_info::parse_functions(&interface);
+
+    match plan_atomic_invocation(args, &functions)? {
+        Some(atomic) => {
+            println!(
+                "✅ Upgrading and migrating atomically through `{}` on {}",
+                atomic_fn, host.id
+            );
+            println!("   Invocation: {}", describe_invocation(&atomic));
+            Ok(atomic)
+        }
+        None if args.allow_two_step => {
+            println!(
+                "⚠️  No `{}` entrypoint found on {}, upgrading and migrating with `{}` in two transactions",
+                atomic_fn, host.id, migrate
+            );
+            println!("⚠️  The new code will run with un-migrated storage in between!");
+            Ok(UpgradeArgs {
+                router: None,
+                ..args.clone()
+            })
+        }
+        None => Err(format!(
+            "❌ No `{}` entrypoint found on {} to upgrade and migrate in a single transaction. \
+            Use --router to upgrade through a router contract, or --allow-two-step to upgrade and migrate in two transactions.",
+            atomic_fn, host.id
+        )),
+    }
+}
+
+/// Invoke the migration function once the upgrade has landed
+pub fn run_migration(args: &UpgradeArgs) -> Result<(), String> {
+    let function = match &args.migrate {
+        Some(function) => function,
+        None => return Ok(()),
+    };
+
+    let command = generate_migrate_command(args, function);
+    println!("\nMigrating: {}", command);
+
+    match capture_command(&command) {
+        Ok(output) => {
+            if !output.trim().is_empty() {
+                println!("{}", output.trim());
+            }
+            println!("✅ Migration `{}` succeeded", function);
+            Ok(())
+        }
+        Err(e) => Err(format!(
+            "❌ Upgrade succeeded but migration `{}` failed. The contract runs the new code with un-migrated storage:\n{}",
+            function,
+            e.trim()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test_contract".to_string(),
+            wasm_hash: "test_hash".to_string(),
+            fee: 300,
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    #[test]
+    fn test_should_migrate() {
+        let mut args = create_test_args();
+        assert!(!should_migrate(&args));
+
+        args.migrate = Some("migrate".to_string());
+        assert!(should_migrate(&args));
+
+        args.build_only = true;
+        assert!(!should_migrate(&args));
+    }
+
+    #[test]
+    fn test_generate_migrate_command() {
+        let mut args = create_test_args();
+        args.migrate_args = vec![("version".to_string(), "2".to_string())];
+
+        let command = generate_migrate_command(&args, "handle_upgrade");
+        assert!(command.starts_with("stellar contract invoke"));
+        assert!(command.contains("--id test_contract"));
+        assert!(command.contains("--fee 300"));
+        assert!(command.ends_with("-- handle_upgrade --version 2"));
+        assert!(!command.contains("new_wasm_hash"));
+    }
+
+    #[test]
+    fn test_plan_atomic_invocation_on_contract() {
+        let mut args = create_test_args();
+        args.migrate = Some("migrate".to_string());
+        args.migrate_args = vec![("version".to_string(), "2".to_string())];
+
+        let functions = contract_info::parse_functions(
+            r#"
+            fn migrate(env: soroban_sdk::Env, version: u32);
+            fn upgrade_and_migrate(env: soroban_sdk::Env, new_wasm_hash: soroban_sdk::BytesN<32>, version: u32);
+            "#,
+        );
+
+        let atomic = plan_atomic_invocation(&args, &functions).unwrap().unwrap();
+        assert_eq!(atomic.upgrade_fn.as_deref(), Some("upgrade_and_migrate"));
+        assert_eq!(atomic.hash_arg.as_deref(), Some("new_wasm_hash"));
+        assert_eq!(
+            atomic.upgrade_args,
+            vec![("version".to_string(), "2".to_string())]
+        );
+        assert_eq!(atomic.migrate, None);
+
+        let command = crate::generate_upgrade_command(&atomic);
+        assert!(command.contains("--id test_contract"));
+        assert!(command.ends_with("-- upgrade_and_migrate --new_wasm_hash test_hash --version 2"));
+    }
+
+    #[test]
+    fn test_plan_atomic_invocation_through_router() {
+        let mut args = create_test_args();
+        args.migrate = Some("migrate".to_string());
+        args.router = Some("router_contract".to_string());
+        args.atomic_fn = Some("upgrade_then_call".to_string());
+
+        let functions = contract_info::parse_functions(
+            r#"
+            fn upgrade_then_call(env: soroban_sdk::Env, contract: soroban_sdk::Address, wasm: soroban_sdk::BytesN<32>, function: soroban_sdk::Symbol);
+            "#,
+        );
+
+        let atomic = plan_atomic_invocation(&args, &functions).unwrap().unwrap();
+        let command = crate::generate_upgrade_command(&atomic);
+        assert!(command.contains("--id router_contract"));
+        assert!(command.ends_with(
+            "-- upgrade_then_call --wasm test_hash --contract test_contract --function migrate"
+        ));
+        assert_eq!(
+            describe_invocation(&atomic),
+            "upgrade_then_call(wasm: test_hash, contract: test_contract, function: migrate)"
+        );
+    }
+
+    #[test]
+    fn test_plan_atomic_invocation_through_router_ambiguous() {
+        let mut args = create_test_args();
+        args.migrate = Some("migrate".to_string());
+        args.router = Some("router_contract".to_string());
+        args.atomic_fn = Some("upgrade_then_call".to_string());
+
+        let functions = contract_info::parse_functions(
+            r#"
+            fn upgrade_then_call(env: soroban_sdk::Env, contract: soroban_sdk::Address, wasm: soroban_sdk::BytesN<32>, function: soroban_sdk::Symbol, admin: soroban_sdk::Address);
+            "#,
+        );
+
+        // Either `Address` parameter could be the upgraded contract
+        let result = plan_atomic_invocation(&args, &functions);
+        assert!(result.unwrap_err().contains("--migrate-arg contract=VALUE"));
+
+        // Once the others are named, the remaining one is filled in
+        args.migrate_args = vec![("admin".to_string(), "GADMIN".to_string())];
+        let atomic = plan_atomic_invocation(&args, &functions).unwrap().unwrap();
+        assert_eq!(
+            atomic.upgrade_args,
+            vec![
+                ("contract".to_string(), "test_contract".to_string()),
+                ("function".to_string(), "migrate".to_string()),
+                ("admin".to_string(), "GADMIN".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_atomic_invocation_not_supported() {
+        let mut args = create_test_args();
+        args.migrate = Some("migrate".to_string());
+
+        let functions =
+            contract_info::parse_functions("fn migrate(env: soroban_sdk::Env, version: u32);");
+        assert!(plan_atomic_invocation(&args, &functions).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_plan_atomic_invocation_missing_argument() {
+        let mut args = create_test_args();
+        args.migrate = Some("migrate".to_string());
+
+        let functions = contract_info::parse_functions(
+            "fn upgrade_and_migrate(env: soroban_sdk::Env, new_wasm_hash: soroban_sdk::BytesN<32>, version: u32);",
+        );
+        let result = plan_atomic_invocation(&args, &functions);
+        assert!(result.unwrap_err().contains("`version`"));
+    }
+
+    #[test]
+    fn test_resolve_atomic_migration_without_migration() {
+        let args = create_test_args();
+
+        let resolved = resolve_atomic_migration(&args).unwrap();
+        assert_eq!(resolved.migrate, None);
+        assert_eq!(resolved.upgrade_fn, None);
+    }
+
+    #[test]
+    fn test_resolve_atomic_migration_router_without_migration() {
+        let mut args = create_test_args();
+        args.router = Some("router_contract".to_string());
+
+        let result = resolve_atomic_migration(&args);
+        assert!(result.unwrap_err().contains("needs --migrate"));
+    }
+
+    #[test]
+    fn test_plan_atomic_invocation_refuses_contract_args() {
+        let mut args = create_test_args();
+        args.migrate = Some("migrate".to_string());
+        args.migrate_args = vec![("version".to_string(), "2".to_string())];
+        args.contract_args = vec!["--operator".to_string(), "GABC".to_string()];
+
+        let functions = contract_info::parse_functions(
+            "fn upgrade_and_migrate(env: soroban_sdk::Env, new_wasm_hash: soroban_sdk::BytesN<32>, version: u32);",
+        );
+        let result = plan_atomic_invocation(&args, &functions);
+        assert!(result.unwrap_err().contains("after `--`"));
+    }
+
+    #[test]
+    fn test_run_migration_without_function() {
+        let args = create_test_args();
+        assert!(run_migration(&args).is_ok());
+    }
+
+    #[test]
+    fn test_run_migration_failure_is_distinct() {
+        let mut args = create_test_args();
+        args.migrate = Some("migrate".to_string());
+
+        // Without a reachable network the migration fails after the (assumed) upgrade
+        let result = run_migration(&args);
+        assert!(result
+            .unwrap_err()
+            .starts_with("❌ Upgrade succeeded but migration `migrate` failed"));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::contract_id;
+use crate::ledger;
+use serde_json::Value;
+
+/// Signing configuration of an account
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountSigners {
+    pub account_id: String,
+    pub master_weight: u32,
+    pub low_threshold: u32,
+    pub medium_threshold: u32,
+    pub high_threshold: u32,
+    /// Additional signers and their weights, by key
+    pub signers: Vec<(String, u32)>,
+}
+
+impl AccountSigners {
+    /// All the keys that can sign for the account, master key included
+    pub fn keys(&self) -> Vec<(String, u32)> {
+        let mut keys = vec![(self.account_id.clone(), self.master_weight)];
+        keys.extend(self.signers.iter().cloned());
+        keys.retain(|(_, weight)| *weight > 0);
+        keys
+    }
+}
+
+/// Parse the signers of an account from a `stellar ledger entry fetch account` JSON
+pub fn parse_account_signers(account_json: &str) -> Result<AccountSigners, String> {
+    let json: Value = serde_json::from_str(account_json)
+        .map_err(|e| format!("Invalid ledger entry JSON: {}", e))?;
+    let account = json["entries"]
+        .get(0)
+        .map(|entry| &entry["val"]["account"])
+        .filter(|account| account.is_object())
+        .ok_or_else(|| "Account not found".to_string())?;
+
+    // Thresholds are 4 bytes: master weight, low, medium and high
+    let thresholds = account["thresholds"]
+        .as_str()
+        .and_then(decode_hex)
+        .filter(|bytes| bytes.len() == 4)
+        .ok_or_else(|| "Account has no valid thresholds".to_string())?;
+
+    let signers = account["signers"]
+        .as_array()
+        .map(|signers| {
+            signers
+                .iter()
+                .filter_map(|signer| {
+                    Some((
+                        signer["key"].as_str()?.to_string(),
+                        signer["weight"].as_u64()? as u32,
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(AccountSigners {
+        account_id: account["account_id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        master_weight: u32::from(thresholds[0]),
+        low_threshold: u32::from(thresholds[1]),
+        medium_threshold: u32::from(thresholds[2]),
+        high_threshold: u32::from(thresholds[3]),
+        signers,
+    })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Signature hint of a key: the hex of the last 4 bytes of its public key
+pub fn key_hint(account_id: &str) -> Result<String, String> {
+    let key = contract_id::decode_account_id(account_id)?;
+    Ok(key[28..]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Hints of the signatures on a decoded envelope
+pub fn signature_hints(envelope: &Value) -> Vec<String> {
+    envelope["tx"]["signatures"]
+        .as_array()
+        .map(|signatures| {
+            signatures
+                .iter()
+                .filter_map(|signature| signature["hint"].as_str())
+                .map(str::to_lowercase)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Merge the signatures of `other` into `base`, both envelopes of the same transaction
+pub fn merge_signatures(base: &Value, other: &Value) -> Result<Value, String> {
+    if base["tx"]["tx"] != other["tx"]["tx"] {
+        return Err("❌ The signed transactions differ, they can't be merged".to_string());
+    }
+
+    let mut merged = base.clone();
+    let signatures = merged["tx"]["signatures"]
+        .as_array_mut()
+        .ok_or_else(|| "Transaction envelope is not a v1 transaction".to_string())?;
+    for signature in other["tx"]["signatures"].as_array().into_iter().flatten() {
+        if !signatures.contains(signature) {
+            signatures.push(signature.clone());
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Signature weight collected for the source account, against its thresholds
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdReport {
+    /// Signers who signed, with their weights
+    pub signed: Vec<(String, u32)>,
+    /// Signers who haven't signed yet
+    pub missing: Vec<(String, u32)>,
+    pub weight: u32,
+    pub low_threshold: u32,
+    pub medium_threshold: u32,
+    pub high_threshold: u32,
+}
+
+impl ThresholdReport {
+    /// Weight needed by an upgrade, an `InvokeHostFunction` being a medium threshold operation
+    pub fn required(&self) -> u32 {
+        self.medium_threshold.max(1)
+    }
+
+    pub fn is_met(&self) -> bool {
+        self.weight >= self.required()
+    }
+
+    /// Print the signers and the thresholds met
+    pub fn print(&self) {
+        println!("Signatures:");
+        for (key, weight) in &self.signed {
+            println!("  ✅ {} (weight {})", key, weight);
+        }
+        for (key, weight) in &self.missing {
+            println!("  ⏳ {} (weight {})", key, weight);
+        }
+
+        let met = |threshold: u32| {
+            if self.weight >= threshold {
+                "✅"
+            } else {
+                "❌"
+            }
+        };
+        println!(
+            "Signed weight {}: {} low ({}), {} medium ({}), {} high ({})",
+            self.weight,
+            met(self.low_threshold),
+            self.low_threshold,
+            met(self.required()),
+            self.required(),
+            met(self.high_threshold),
+            self.high_threshold
+        );
+    }
+}
+
+/// Match the signature hints with the account's signers and total their weight
+pub fn evaluate_thresholds(account: &AccountSigners, hints: &[String]) -> ThresholdReport {
+    let (signed, missing): (Vec<_>, Vec<_>) = account
+        .keys()
+        .into_iter()
+        .partition(|(key, _)| key_hint(key).is_ok_and(|hint| hints.contains(&hint)));
+
+    ThresholdReport {
+        weight: signed.iter().map(|(_, weight)| weight).sum(),
+        signed,
+        missing,
+        low_threshold: account.low_threshold,
+        medium_threshold: account.medium_threshold,
+        high_threshold: account.high_threshold,
+    }
+}
+
+/// Source account of a decoded envelope
+pub fn source_account(envelope: &Value) -> Result<String, String> {
+    envelope["tx"]["tx"]["source_account"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Transaction has no source account".to_string())
+}
+
+/// Check the signatures on a decoded envelope against its source account's thresholds
+pub fn check_thresholds(envelope: &Value, network_flags: &str) -> Result<ThresholdReport, String> {
+    let source = source_account(envelope)?;
+    let account = parse_account_signers(&ledger::fetch_account(&source, network_flags)?)?;
+
+    Ok(evaluate_thresholds(&account, &signature_hints(envelope)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMIN: &str = "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54";
+    const SIGNER_A: &str = "GBZXN7PIRZGNMHGA7MUUUF4GWPY5AYPV6LY4UV2GL6VJGIQRXFDNMADI";
+    const SIGNER_B: &str = "GCEZWKCA5VLDNRLN3RPRJMRZOX3Z6G5CHCGSNFHEYVXM3XOJMDS674JZ";
+
+    fn account_json(thresholds: &str) -> String {
+        format!(
+            r#"{{"entries":[{{"val":{{"account":{{"account_id":"{}","balance":"100","thresholds":"{}","signers":[{{"key":"{}","weight":1}},{{"key":"{}","weight":2}}]}}}}}}]}}"#,
+            ADMIN, thresholds, SIGNER_A, SIGNER_B
+        )
+    }
+
+    fn envelope(hints: &[String]) -> Value {
+        let signatures: Vec<Value> = hints
+            .iter()
+            .map(|hint| serde_json::json!({ "hint": hint, "signature": "00" }))
+            .collect();
+        serde_json::json!({
+            "tx": {
+                "tx": { "source_account": ADMIN, "operations": [] },
+                "signatures": signatures
+            }
+        })
+    }
+
+    #[test]
+    fn test_parse_account_signers() {
+        let account = parse_account_signers(&account_json("00010203")).unwrap();
+        assert_eq!(account.account_id, ADMIN);
+        assert_eq!(account.master_weight, 0);
+        assert_eq!(
+            (
+                account.low_threshold,
+                account.medium_threshold,
+                account.high_threshold
+            ),
+            (1, 2, 3)
+        );
+        // The master key was disabled with a weight of 0
+        assert_eq!(
+            account.keys(),
+            vec![(SIGNER_A.to_string(), 1), (SIGNER_B.to_string(), 2)]
+        );
+
+        assert!(parse_account_signers(r#"{"entries":[]}"#).is_err());
+        assert!(parse_account_signers(&account_json("0001")).is_err());
+    }
+
+    #[test]
+    fn test_key_hint() {
+        assert_eq!(key_hint(ADMIN).unwrap().len(), 8);
+        assert_ne!(key_hint(ADMIN).unwrap(), key_hint(SIGNER_A).unwrap());
+        assert!(key_hint("CABC").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_thresholds() {
+        let account = parse_account_signers(&account_json("00010203")).unwrap();
+
+        let report = evaluate_thresholds(&account, &[key_hint(SIGNER_A).unwrap()]);
+        assert_eq!(report.weight, 1);
+        assert_eq!(report.missing, vec![(SIGNER_B.to_string(), 2)]);
+        assert!(!report.is_met());
+
+        let hints = [key_hint(SIGNER_A).unwrap(), key_hint(SIGNER_B).unwrap()];
+        let report = evaluate_thresholds(&account, &hints);
+        assert_eq!(report.weight, 3);
+        assert!(report.is_met());
+    }
+
+    #[test]
+    fn test_single_signer_thresholds() {
+        // A default account: master weight 1 and thresholds of 0
+        let account = parse_account_signers(&account_json("01000000")).unwrap();
+
+        assert!(!evaluate_thresholds(&account, &[]).is_met());
+        assert!(evaluate_thresholds(&account, &[key_hint(ADMIN).unwrap()]).is_met());
+    }
+
+    #[test]
+    fn test_merge_signatures() {
+        let a = envelope(&[key_hint(SIGNER_A).unwrap()]);
+        let b = envelope(&[key_hint(SIGNER_B).unwrap(), key_hint(SIGNER_A).unwrap()]);
+
+        let merged = merge_signatures(&a, &b).unwrap();
+        assert_eq!(
+            signature_hints(&merged),
+            vec![key_hint(SIGNER_A).unwrap(), key_hint(SIGNER_B).unwrap()]
+        );
+
+        let mut other = envelope(&[]);
+        other["tx"]["tx"]["source_account"] = Value::from(SIGNER_A);
+        assert!(merge_signatures(&a, &other).unwrap_err().contains("differ"));
+    }
+
+    #[test]
+    fn test_source_account() {
+        assert_eq!(source_account(&envelope(&[])).unwrap(), ADMIN);
+        assert!(source_account(&serde_json::json!({})).is_err());
+    }
+}
@@ -0,0 +1,556 @@
+use crate::history::{self, HistoryEntry};
+use crate::transaction::NotBefore;
+use crate::{
+    authorization, capture_command, execute_command, network_flags, prepare_upgrade, simulation,
+    verification, BuildArgs, SignArgs, SubmitArgs, UpgradeArgs,
+};
+use crate::{ledger, multisig, transaction, wasm};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version of the transaction file format
+pub const TRANSACTION_FORMAT: u32 = 1;
+
+/// Passphrase of a well-known network, needed to sign without network access
+pub fn known_network_passphrase(network: &str) -> Option<&'static str> {
+    match network {
+        "mainnet" => Some("Public Global Stellar Network ; September 2015"),
+        "testnet" => Some("Test SDF Network ; September 2015"),
+        "futurenet" => Some("Test SDF Future Network ; October 2022"),
+        "local" => Some("Standalone Network ; February 2017"),
+        _ => None,
+    }
+}
+
+/// An upgrade transaction envelope, annotated with what it does and the checks it passed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionFile {
+    pub format: u32,
+    /// Contract invoked by the transaction, the router for routed upgrades
+    pub target: String,
+    pub function: String,
+    pub wasm_hash: String,
+    pub network: String,
+    pub network_passphrase: Option<String>,
+    /// Security checks the upgrade passed when built
+    pub checks: Vec<String>,
+    /// Simulated fees, in stroops
+    pub fee: u64,
+    pub resource_fee: u64,
+    /// WASM hash the contract ran when the transaction was built
+    #[serde(default)]
+    pub current_wasm_hash: Option<String>,
+    /// Base64 XDR of the transaction envelope, signatures included
+    pub envelope: String,
+    /// Resolved arguments of the upgrade
+    pub upgrade: UpgradeArgs,
+}
+
+impl TransactionFile {
+    /// Annotate the envelope of an upgrade
+    pub fn new(
+        upgrade: &UpgradeArgs,
+        checks: Vec<String>,
+        fee: u64,
+        resource_fee: u64,
+        envelope: &str,
+    ) -> Self {
+        TransactionFile {
+            format: TRANSACTION_FORMAT,
+            target: upgrade.router.clone().unwrap_or_else(|| upgrade.id.clone()),
+            function: upgrade
+                .upgrade_fn
+                .clone()
+                .unwrap_or_else(|| "upgrade".to_string()),
+            wasm_hash: upgrade.wasm_hash.clone(),
+            network: upgrade.network.clone(),
+            network_passphrase: upgrade
+                .network_passphrase
+                .clone()
+                .or_else(|| known_network_passphrase(&upgrade.network).map(str::to_string)),
+            checks,
+            fee,
+            resource_fee,
+            current_wasm_hash: None,
+            envelope: envelope.trim().to_string(),
+            upgrade: upgrade.clone(),
+        }
+    }
+
+    /// Read a transaction file
+    pub fn read(path: &str) -> Result<Self, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let file: TransactionFile = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid transaction file {}: {}", path, e))?;
+
+        if file.format != TRANSACTION_FORMAT {
+            return Err(format!(
+                "Unsupported transaction file format {}, expected {}",
+                file.format, TRANSACTION_FORMAT
+            ));
+        }
+        Ok(file)
+    }
+
+    /// Write the transaction file
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the transaction: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// Check the decoded envelope still performs the annotated upgrade, and nothing else
+    pub fn verify_envelope(&self, envelope: &Value) -> Result<(), String> {
+        let expected = TransactionFile::new(&self.upgrade, vec![], 0, 0, "");
+        if (&expected.target, &expected.function, &expected.wasm_hash)
+            != (&self.target, &self.function, &self.wasm_hash)
+        {
+            return Err("❌ Transaction annotations don't match its upgrade arguments".to_string());
+        }
+
+        let tx = transaction::transaction(envelope)?;
+        let invoke = transaction::invoke_host_function(tx)?;
+        let call = invoke["host_function"]
+            .get("invoke_contract")
+            .ok_or_else(|| "❌ Transaction doesn't invoke a contract".to_string())?;
+
+        let contract = call["contract_address"].as_str().unwrap_or("?");
+        if contract != self.target {
+            return Err(format!(
+                "❌ Transaction invokes contract {}, expected {}",
+                contract, self.target
+            ));
+        }
+        let function = call["function_name"].as_str().unwrap_or("?");
+        if function != self.function {
+            return Err(format!(
+                "❌ Transaction calls `{}`, expected `{}`",
+                function, self.function
+            ));
+        }
+        let passes_hash = call["args"].as_array().is_some_and(|args| {
+            args.iter().any(|arg| {
+                arg["bytes"]
+                    .as_str()
+                    .is_some_and(|bytes| bytes.eq_ignore_ascii_case(&self.wasm_hash))
+            })
+        });
+        if !passes_hash {
+            return Err(format!(
+                "❌ Transaction doesn't pass WASM hash {} to `{}`",
+                self.wasm_hash, self.function
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Decode the envelope and verify it, returning the decoded envelope
+    pub fn verify(&self) -> Result<Value, String> {
+        let envelope = transaction::decode_envelope(&self.envelope)?;
+        self.verify_envelope(&envelope)?;
+        println!(
+            "✅ Transaction targets `{}` on {} with WASM {}",
+            self.function, self.target, self.wasm_hash
+        );
+        Ok(envelope)
+    }
+
+    /// Print what the transaction does, for the signers to review
+    pub fn print(&self) {
+        println!("Upgrade transaction:");
+        println!("  Contract:  {} on {}", self.upgrade.id, self.network);
+        if self.target != self.upgrade.id {
+            println!("  Router:    {}", self.target);
+        }
+        println!("  Function:  {}", self.function);
+        match &self.current_wasm_hash {
+            Some(current) => println!("  WASM:      {} -> {}", current, self.wasm_hash),
+            None => println!("  WASM:      {}", self.wasm_hash),
+        }
+        println!("  Checks:    {}", self.checks.join(", "));
+        println!(
+            "  Fee:       {} stroops ({} resource fee)",
+            self.fee, self.resource_fee
+        );
+    }
+}
+
+/// Generate the command signing the envelope, offline when the network passphrase is known
+pub fn generate_sign_command(file: &TransactionFile, key: &str) -> String {
+    let network = match &file.network_passphrase {
+        Some(passphrase) => format!("--network-passphrase '{}'", passphrase),
+        None => format!("--network {}", file.network),
+    };
+
+    format!(
+        "stellar tx sign --sign-with-key {} {} {}",
+        key, network, file.envelope
+    )
+}
+
+/// Generate the command submitting the envelope
+pub fn generate_send_command(file: &TransactionFile) -> String {
+    format!(
+        "stellar tx send {} {}",
+        network_flags(&file.upgrade),
+        file.envelope
+    )
+}
+
+/// Run the build command, writing the checked and simulated unsigned transaction
+pub fn run_build(args: &BuildArgs) -> Result<(), String> {
+    if args.upgrade.force {
+        return Err(
+            "A transaction file records the security checks, it can't be built with --force"
+                .to_string(),
+        );
+    }
+
+    let (upgrade, checks) = prepare_upgrade(&args.upgrade, true)?;
+    if upgrade.migrate.is_some() {
+        return Err(
+            "❌ A two-step migration needs a second transaction and can't be signed offline. \
+            Upgrade and migrate through an atomic entrypoint instead."
+                .to_string(),
+        );
+    }
+
+    if !wasm::is_installed(&upgrade)? {
+        return Err(format!(
+            "❌ WASM {} is not installed on {}. Upload it with `stellar contract upload` before building the transaction.",
+            upgrade.wasm_hash, upgrade.network
+        ));
+    }
+
+    // Soroban transactions need the resources found by simulation to be submitted
+    let report = simulation::simulate_upgrade(&upgrade)?;
+    report.print();
+    println!();
+    let source_address = authorization::resolve_source_address(&upgrade.source)?;
+    authorization::analyze_authorization(&report.auth, &source_address, upgrade.force)?;
+    println!();
+
+    // Submitting is refused if the contract changes before then
+    let instance = ledger::fetch_contract_instance(&upgrade.id, &network_flags(&upgrade))?;
+    let mut xdr = report.xdr.clone();
+    if let Some(value) = args.not_before {
+        let not_before = NotBefore::from_value(value);
+        if not_before.is_reached(ledger::extract_latest_ledger(&instance)?, history::now()) {
+            return Err(format!(
+                "❌ Not before {} is already in the past",
+                not_before
+            ));
+        }
+
+        // The bound is part of what gets signed, so it can't be moved earlier afterwards
+        let mut envelope = transaction::decode_envelope(&xdr)?;
+        not_before.apply(transaction::transaction_mut(&mut envelope)?)?;
+        xdr = transaction::encode_envelope(&envelope)?;
+        println!(
+            "✅ The transaction can't be submitted before {}\n",
+            not_before
+        );
+    }
+
+    let mut file = TransactionFile::new(&upgrade, checks, report.fee, report.resource_fee, &xdr);
+    file.current_wasm_hash = Some(ledger::extract_executable_hash(&instance)?);
+    file.verify()?;
+    file.write(&args.out)?;
+
+    println!("\n✅ Unsigned transaction written to {}", args.out);
+    println!(
+        "   Sign it with: stellar upgrader sign {} --sign-with-key <identity>",
+        args.out
+    );
+    Ok(())
+}
+
+/// Run the sign command, adding a signature to the transaction file
+pub fn run_sign(args: &SignArgs) -> Result<(), String> {
+    if args.sign_with_key.is_none() && args.add_signatures.is_empty() {
+        return Err("Nothing to sign with, pass --sign-with-key or --add-signature".to_string());
+    }
+
+    let mut file = TransactionFile::read(&args.file)?;
+    file.print();
+    println!();
+    let mut envelope = file.verify()?;
+
+    // Collect the signatures other signers added to their copy of the transaction
+    if !args.add_signatures.is_empty() {
+        for path in &args.add_signatures {
+            let other = TransactionFile::read(path)?.verify()?;
+            envelope = multisig::merge_signatures(&envelope, &other)?;
+            println!("✅ Added the signatures of {}", path);
+        }
+        file.envelope = transaction::encode_envelope(&envelope)?;
+        envelope = file.verify()?;
+    }
+
+    if let Some(key) = &args.sign_with_key {
+        let signed = capture_command(&generate_sign_command(&file, key))
+            .map_err(|e| format!("❌ Failed to sign the transaction:\n{}", e.trim()))?;
+        file.envelope = signed.trim().to_string();
+        envelope = file.verify()?;
+    }
+
+    let out = args.out.as_ref().unwrap_or(&args.file);
+    file.write(out)?;
+    println!("✅ Signed transaction written to {}\n", out);
+
+    // Signing machines may be air-gapped, so the thresholds are only reported when reachable
+    match multisig::check_thresholds(&envelope, &network_flags(&file.upgrade)) {
+        Ok(report) => report.print(),
+        Err(e) => println!(
+            "⚠️  Couldn't check the signatures against the source account's thresholds: {}",
+            e.trim()
+        ),
+    }
+    Ok(())
+}
+
+/// Check the contract is unchanged since the build, and the transaction's lower bound reached
+///
+/// `instance_json` is the contract instance ledger entry, `now` the current Unix timestamp.
+pub fn check_submittable(
+    file: &TransactionFile,
+    envelope: &Value,
+    instance_json: &str,
+    now: u64,
+) -> Result<(), String> {
+    let current_wasm_hash = ledger::extract_executable_hash(instance_json)?;
+    if let Some(built) = &file.current_wasm_hash {
+        if built != &current_wasm_hash {
+            return Err(format!(
+                "❌ {} changed since the transaction was built: current WASM hash changed from {} to {}, build a new transaction",
+                file.upgrade.id, built, current_wasm_hash
+            ));
+        }
+    }
+
+    let bounds = transaction::bounds(transaction::transaction(envelope)?);
+    for not_before in NotBefore::from_bounds(&bounds) {
+        let latest_ledger = ledger::extract_latest_ledger(instance_json)?;
+        if !not_before.is_reached(latest_ledger, now) {
+            return Err(format!(
+                "❌ The transaction can't be submitted before {}, the latest ledger is {}",
+                not_before, latest_ledger
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the submit command, broadcasting the signed transaction
+pub fn run_submit(args: &SubmitArgs) -> Result<(), String> {
+    let file = TransactionFile::read(&args.file)?;
+    file.print();
+    println!();
+
+    let envelope = file.verify()?;
+    let instance =
+        ledger::fetch_contract_instance(&file.upgrade.id, &network_flags(&file.upgrade))?;
+    check_submittable(&file, &envelope, &instance, history::now())?;
+    let report = multisig::check_thresholds(&envelope, &network_flags(&file.upgrade))?;
+    report.print();
+    if !report.is_met() {
+        return Err(format!(
+            "❌ Signed weight {} is below the {} required by the source account. \
+            Collect more signatures with: stellar upgrader sign {} --add-signature <signed file>",
+            report.weight,
+            report.required(),
+            args.file
+        ));
+    }
+    println!();
+
+    let entry = HistoryEntry::fetch(&file.upgrade, &file.checks);
+    let command = generate_send_command(&file);
+    println!("Executing: {}", command);
+    let output = execute_command(&command)?;
+    history::record(entry, &output);
+
+    if verification::should_verify(&file.upgrade) {
+        verification::verify_upgrade(&file.upgrade)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACT: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+    const HASH: &str = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: CONTRACT.to_string(),
+            wasm_hash: HASH.to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    fn create_test_file() -> TransactionFile {
+        TransactionFile::new(
+            &create_test_args(),
+            vec!["Version Check".to_string()],
+            1000,
+            900,
+            "AAAAAgAAAAA=\n",
+        )
+    }
+
+    fn envelope(contract: &str, function: &str, hash: &str) -> Value {
+        serde_json::json!({
+            "tx": {
+                "tx": {
+                    "operations": [{
+                        "body": {
+                            "invoke_host_function": {
+                                "host_function": {
+                                    "invoke_contract": {
+                                        "contract_address": contract,
+                                        "function_name": function,
+                                        "args": [{ "bytes": hash }]
+                                    }
+                                },
+                                "auth": []
+                            }
+                        }
+                    }]
+                },
+                "signatures": []
+            }
+        })
+    }
+
+    #[test]
+    fn test_new_transaction_file() {
+        let file = create_test_file();
+        assert_eq!(file.target, CONTRACT);
+        assert_eq!(file.function, "upgrade");
+        assert_eq!(file.envelope, "AAAAAgAAAAA=");
+        assert_eq!(
+            file.network_passphrase.as_deref(),
+            Some("Test SDF Network ; September 2015")
+        );
+
+        let mut args = create_test_args();
+        args.router = Some("CROUTER".to_string());
+        args.upgrade_fn = Some("upgrade_and_migrate".to_string());
+        args.network = "custom".to_string();
+        let file = TransactionFile::new(&args, vec![], 0, 0, "");
+        assert_eq!(file.target, "CROUTER");
+        assert_eq!(file.function, "upgrade_and_migrate");
+        assert_eq!(file.network_passphrase, None);
+    }
+
+    #[test]
+    fn test_verify_envelope() {
+        let file = create_test_file();
+        assert!(file
+            .verify_envelope(&envelope(CONTRACT, "upgrade", HASH))
+            .is_ok());
+
+        assert!(file
+            .verify_envelope(&envelope("CEVIL", "upgrade", HASH))
+            .unwrap_err()
+            .contains("invokes contract CEVIL"));
+        assert!(file
+            .verify_envelope(&envelope(CONTRACT, "transfer", HASH))
+            .unwrap_err()
+            .contains("calls `transfer`"));
+        assert!(file
+            .verify_envelope(&envelope(CONTRACT, "upgrade", &"1".repeat(64)))
+            .unwrap_err()
+            .contains("doesn't pass WASM hash"));
+    }
+
+    #[test]
+    fn test_verify_envelope_edited_annotations() {
+        let mut file = create_test_file();
+        file.wasm_hash = "1".repeat(64);
+
+        assert!(file
+            .verify_envelope(&envelope(CONTRACT, "upgrade", &"1".repeat(64)))
+            .unwrap_err()
+            .contains("annotations don't match"));
+    }
+
+    #[test]
+    fn test_generate_sign_command() {
+        let mut file = create_test_file();
+        assert_eq!(
+            generate_sign_command(&file, "admin"),
+            "stellar tx sign --sign-with-key admin --network-passphrase 'Test SDF Network ; September 2015' AAAAAgAAAAA="
+        );
+
+        file.network_passphrase = None;
+        assert_eq!(
+            generate_sign_command(&file, "admin"),
+            "stellar tx sign --sign-with-key admin --network testnet AAAAAgAAAAA="
+        );
+    }
+
+    #[test]
+    fn test_generate_send_command() {
+        assert_eq!(
+            generate_send_command(&create_test_file()),
+            "stellar tx send --network testnet AAAAAgAAAAA="
+        );
+    }
+
+    fn instance(wasm_hash: &str, latest_ledger: u64) -> String {
+        format!(
+            r#"{{"latest_ledger":{},"entries":[{{"val":{{"contract_data":{{"val":{{"contract_instance":{{"executable":{{"wasm":"{}"}},"storage":null}}}}}}}}}}]}}"#,
+            latest_ledger, wasm_hash
+        )
+    }
+
+    #[test]
+    fn test_check_submittable() {
+        let current = "1".repeat(64);
+        let mut file = create_test_file();
+        file.current_wasm_hash = Some(current.clone());
+        let mut envelope = envelope(CONTRACT, "upgrade", HASH);
+        assert!(check_submittable(&file, &envelope, &instance(&current, 1000), 0).is_ok());
+
+        // The contract was upgraded by someone else in the meantime
+        let err = check_submittable(&file, &envelope, &instance(HASH, 1000), 0).unwrap_err();
+        assert!(err.contains("changed since the transaction was built"));
+
+        NotBefore::Ledger(2000)
+            .apply(&mut envelope["tx"]["tx"])
+            .unwrap();
+        let err = check_submittable(&file, &envelope, &instance(&current, 1999), 0).unwrap_err();
+        assert!(err.contains("can't be submitted before ledger 2000"));
+        assert!(check_submittable(&file, &envelope, &instance(&current, 2000), 0).is_ok());
+
+        NotBefore::Time(1_700_000_000)
+            .apply(&mut envelope["tx"]["tx"])
+            .unwrap();
+        assert!(
+            check_submittable(&file, &envelope, &instance(&current, 2000), 1_699_999_999).is_err()
+        );
+        assert!(
+            check_submittable(&file, &envelope, &instance(&current, 2000), 1_700_000_000).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_transaction_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("upgrade-tx-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let file = create_test_file();
+        file.write(path).unwrap();
+        assert_eq!(TransactionFile::read(path).unwrap(), file);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,331 @@
+use crate::security_checks::version_check::VersionCheck;
+use crate::{authorization, ledger, simulation, wasm};
+use crate::{
+    contract_id, execute_upgrade, generate_upgrade_command, network_flags, prepare_upgrade,
+    ApplyArgs, PlanArgs, UpgradeArgs,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Version of the plan file format
+pub const PLAN_FORMAT: u32 = 1;
+
+/// An upgrade checked and simulated ahead of time, to be applied as is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpgradePlan {
+    pub format: u32,
+    pub contract_id: String,
+    pub alias: Option<String>,
+    pub network: String,
+    /// WASM hash the contract runs at planning time
+    pub current_wasm_hash: String,
+    pub new_wasm_hash: String,
+    /// Binary version the contract runs at planning time
+    pub current_version: String,
+    pub new_version: String,
+    /// Security checks that passed
+    pub checks: Vec<String>,
+    /// Simulated fees, in stroops
+    pub fee: Option<u64>,
+    pub resource_fee: Option<u64>,
+    /// Exact invocation of the upgrade
+    pub command: String,
+    /// Resolved arguments of the upgrade
+    pub upgrade: UpgradeArgs,
+}
+
+impl UpgradePlan {
+    /// SHA-256 of the plan, detecting a corrupted plan file
+    ///
+    /// Computed over the plan serialized with sorted keys, so it doesn't depend on how the
+    /// file was formatted. Anyone able to edit the plan can recompute it, it doesn't detect
+    /// tampering.
+    pub fn checksum(&self) -> String {
+        let json = serde_json::to_value(self)
+            .expect("plan serializes to JSON")
+            .to_string();
+        format!("{:x}", Sha256::digest(json.as_bytes()))
+    }
+
+    /// Print the plan
+    pub fn print(&self) {
+        let contract = match &self.alias {
+            Some(alias) => format!("{} ({})", alias, self.contract_id),
+            None => self.contract_id.clone(),
+        };
+
+        println!("Upgrade plan:");
+        println!("  Contract:  {} on {}", contract, self.network);
+        println!(
+            "  WASM:      {} -> {}",
+            self.current_wasm_hash, self.new_wasm_hash
+        );
+        println!(
+            "  Version:   {} -> {}",
+            self.current_version, self.new_version
+        );
+        println!("  Checks:    {}", self.checks.join(", "));
+        if let (Some(fee), Some(resource_fee)) = (self.fee, self.resource_fee) {
+            println!(
+                "  Fee:       {} stroops ({} resource fee)",
+                fee, resource_fee
+            );
+        }
+        println!("  Command:   {}", self.command);
+    }
+
+    /// Describe how the on-chain state differs from the state at planning time
+    pub fn state_changes(&self, current_wasm_hash: &str, current_version: &str) -> Vec<String> {
+        let mut changes = Vec::new();
+        if current_wasm_hash != self.current_wasm_hash {
+            changes.push(format!(
+                "current WASM hash changed from {} to {}",
+                self.current_wasm_hash, current_wasm_hash
+            ));
+        }
+        if current_version != self.current_version {
+            changes.push(format!(
+                "current version changed from {} to {}",
+                self.current_version, current_version
+            ));
+        }
+        changes
+    }
+}
+
+/// Plan file, the plan along with its checksum
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanFile {
+    pub plan: UpgradePlan,
+    pub checksum: String,
+}
+
+impl PlanFile {
+    pub fn new(plan: UpgradePlan) -> Self {
+        let checksum = plan.checksum();
+        PlanFile { plan, checksum }
+    }
+
+    /// Parse a plan file, refusing corrupted plans
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let file: PlanFile =
+            serde_json::from_str(content).map_err(|e| format!("Invalid plan file: {}", e))?;
+
+        if file.plan.format != PLAN_FORMAT {
+            return Err(format!(
+                "Unsupported plan format {}, expected {}",
+                file.plan.format, PLAN_FORMAT
+            ));
+        }
+        if file.plan.checksum() != file.checksum {
+            return Err(
+                "❌ Plan checksum mismatch, the plan file is corrupted or was edited".to_string(),
+            );
+        }
+        if generate_upgrade_command(&file.plan.upgrade) != file.plan.command {
+            return Err("❌ Plan command doesn't match its upgrade arguments".to_string());
+        }
+
+        Ok(file)
+    }
+}
+
+/// Read the deployed WASM hash and binary version of the contract
+fn fetch_current_state(args: &UpgradeArgs) -> Result<(String, String), String> {
+    let version_check = VersionCheck::new();
+
+    let wasm_hash = ledger::fetch_contract_wasm_hash(&args.id, &network_flags(args))?;
+    let version =
+        version_check.extract_binver(&version_check.get_contract_metadata(args, None)?)?;
+
+    Ok((wasm_hash, version))
+}
+
+/// Check, simulate and record the upgrade in a plan
+pub fn create_plan(args: &UpgradeArgs) -> Result<UpgradePlan, String> {
+    if args.force {
+        return Err(
+            "A plan records the security checks, it can't be made with --force".to_string(),
+        );
+    }
+
+    let (upgrade, checks) = prepare_upgrade(args, true)?;
+    let (current_wasm_hash, current_version) = fetch_current_state(&upgrade)?;
+
+    let version_check = VersionCheck::new();
+    let new_version = version_check.extract_binver(
+        &version_check.get_contract_metadata(&upgrade, Some(&upgrade.wasm_hash))?,
+    )?;
+
+    let simulation = if upgrade.skip_simulation {
+        None
+    } else if !wasm::is_installed(&upgrade)? {
+        // Planning has no side effects, the --wasm file is only uploaded on apply
+        println!("⚠️  Skipping the simulation until the new WASM is uploaded on apply\n");
+        None
+    } else {
+        let report = simulation::simulate_upgrade(&upgrade)?;
+        report.print();
+        println!();
+
+        let source_address = authorization::resolve_source_address(&upgrade.source)?;
+        authorization::analyze_authorization(&report.auth, &source_address, upgrade.force)?;
+        println!();
+        Some(report)
+    };
+
+    Ok(UpgradePlan {
+        format: PLAN_FORMAT,
+        contract_id: upgrade.id.clone(),
+        alias: upgrade.id_alias.clone(),
+        network: upgrade.network.clone(),
+        current_wasm_hash,
+        new_wasm_hash: upgrade.wasm_hash.clone(),
+        current_version,
+        new_version,
+        checks,
+        fee: simulation.as_ref().map(|report| report.fee),
+        resource_fee: simulation.as_ref().map(|report| report.resource_fee),
+        command: generate_upgrade_command(&upgrade),
+        upgrade,
+    })
+}
+
+/// Run the plan command
+pub fn run_plan(args: &PlanArgs) -> Result<(), String> {
+    let plan = create_plan(&args.upgrade)?;
+    plan.print();
+
+    let file = PlanFile::new(plan);
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize the plan: {}", e))?;
+    std::fs::write(&args.out, json).map_err(|e| format!("Failed to write {}: {}", args.out, e))?;
+
+    println!("\n✅ Plan written to {}", args.out);
+    println!("   Apply it with: stellar upgrader apply {}", args.out);
+    Ok(())
+}
+
+/// Run the apply command, executing the plan if the contract didn't change since planning
+pub fn run_apply(args: &ApplyArgs) -> Result<(), String> {
+    let content = std::fs::read_to_string(&args.plan)
+        .map_err(|e| format!("Failed to read {}: {}", args.plan, e))?;
+    let plan = PlanFile::parse(&content)?.plan;
+    plan.print();
+    println!();
+
+    let (current_wasm_hash, current_version) = fetch_current_state(&plan.upgrade)?;
+    let changes = plan.state_changes(&current_wasm_hash, &current_version);
+    if !changes.is_empty() {
+        return Err(format!(
+            "❌ {} changed since planning, create a new plan:\n  - {}",
+            contract_id::display_contract(&plan.upgrade),
+            changes.join("\n  - ")
+        ));
+    }
+    println!("✅ On-chain state matches the plan");
+
+    execute_upgrade(&plan.upgrade, &plan.checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            wasm_hash: "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"
+                .to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    fn create_test_plan() -> UpgradePlan {
+        let upgrade = create_test_args();
+
+        UpgradePlan {
+            format: PLAN_FORMAT,
+            contract_id: upgrade.id.clone(),
+            alias: None,
+            network: upgrade.network.clone(),
+            current_wasm_hash: "1".repeat(64),
+            new_wasm_hash: upgrade.wasm_hash.clone(),
+            current_version: "1.0.0".to_string(),
+            new_version: "1.1.0".to_string(),
+            checks: vec!["Version Check".to_string()],
+            fee: Some(1000),
+            resource_fee: Some(900),
+            command: generate_upgrade_command(&upgrade),
+            upgrade,
+        }
+    }
+
+    #[test]
+    fn test_plan_file_round_trip() {
+        let file = PlanFile::new(create_test_plan());
+        let json = serde_json::to_string_pretty(&file).unwrap();
+
+        assert_eq!(PlanFile::parse(&json).unwrap(), file);
+    }
+
+    #[test]
+    fn test_plan_file_reformatted() {
+        let file = PlanFile::new(create_test_plan());
+
+        // Reordering the keys or the whitespace of the file keeps the checksum
+        let json: serde_json::Value = serde_json::to_value(&file).unwrap();
+        assert_eq!(PlanFile::parse(&json.to_string()).unwrap(), file);
+    }
+
+    #[test]
+    fn test_plan_file_corrupted() {
+        let mut file = PlanFile::new(create_test_plan());
+        file.plan.upgrade.fee = 100_000;
+        file.plan.command = generate_upgrade_command(&file.plan.upgrade);
+        let json = serde_json::to_string(&file).unwrap();
+
+        assert!(PlanFile::parse(&json)
+            .unwrap_err()
+            .contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn test_plan_file_command_mismatch() {
+        let mut plan = create_test_plan();
+        plan.command = "stellar contract invoke --id CEVIL -- upgrade".to_string();
+        let json = serde_json::to_string(&PlanFile::new(plan)).unwrap();
+
+        assert!(PlanFile::parse(&json)
+            .unwrap_err()
+            .contains("doesn't match its upgrade arguments"));
+    }
+
+    #[test]
+    fn test_plan_file_format() {
+        let mut plan = create_test_plan();
+        plan.format = PLAN_FORMAT + 1;
+        let json = serde_json::to_string(&PlanFile::new(plan)).unwrap();
+
+        assert!(PlanFile::parse(&json)
+            .unwrap_err()
+            .contains("Unsupported plan format"));
+    }
+
+    #[test]
+    fn test_state_changes() {
+        let plan = create_test_plan();
+        assert!(plan.state_changes(&"1".repeat(64), "1.0.0").is_empty());
+
+        let changes = plan.state_changes(&"2".repeat(64), "1.0.1");
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].starts_with("current WASM hash changed"));
+        assert!(changes[1].contains("from 1.0.0 to 1.0.1"));
+    }
+
+    #[test]
+    fn test_create_plan_refuses_force() {
+        let mut args = create_test_args();
+        args.force = true;
+        assert!(create_plan(&args).unwrap_err().contains("--force"));
+    }
+}
@@ -0,0 +1,296 @@
+use crate::config::Config;
+use crate::transaction::as_u64;
+use crate::{
+    capture_command, contract_id, history, ledger, network_flags, run_upgrade, RollbackArgs,
+    UpgradeArgs,
+};
+use clap::Parser;
+use serde_json::Value;
+
+/// Ledgers of events an RPC retains by default, about 7 days of 5 second ledgers
+const EVENT_RETENTION_LEDGERS: u64 = 120_960;
+
+/// Maximum number of events fetched when looking for the last upgrade
+const EVENT_COUNT: u32 = 1000;
+
+/// Topic of the event emitted when a contract's executable is updated
+const EXECUTABLE_UPDATE_TOPIC: &str = "executable_update";
+
+/// An executable update of the contract, found in its events
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeEvent {
+    pub ledger: u64,
+    /// WASM hash replaced by the update, `None` for non-WASM executables
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+}
+
+/// Decode a base64 `ScVal` into its JSON representation
+fn decode_scval(xdr: &str) -> Result<Value, String> {
+    let command = format!(
+        "echo {} | stellar xdr decode --type ScVal --output json",
+        xdr.trim()
+    );
+    let json = capture_command(&command).map_err(|e| format!("Failed to decode event: {}", e))?;
+
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse event: {}", e))
+}
+
+/// Find the WASM hash in a decoded executable, e.g. `{"vec":[{"symbol":"Wasm"},{"bytes":"9ab3..."}]}`
+fn executable_hash(executable: &Value) -> Option<String> {
+    match executable {
+        Value::String(s) if s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Some(s.to_lowercase())
+        }
+        Value::Array(values) => values.iter().find_map(executable_hash),
+        Value::Object(fields) => fields.values().find_map(executable_hash),
+        _ => None,
+    }
+}
+
+/// Parse the executable updates out of a `stellar events --output json` output
+pub fn parse_upgrade_events(
+    output: &str,
+    decode: impl Fn(&str) -> Result<Value, String>,
+) -> Result<Vec<UpgradeEvent>, String> {
+    let mut events = Vec::new();
+
+    for value in serde_json::Deserializer::from_str(output).into_iter::<Value>() {
+        let value = value.map_err(|e| format!("Invalid events JSON: {}", e))?;
+        let batch = match value {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+
+        for event in batch {
+            let topics = event
+                .get("topic")
+                .or_else(|| event.get("topics"))
+                .and_then(Value::as_array);
+            let Some(topics) = topics.filter(|topics| topics.len() == 3) else {
+                continue;
+            };
+            let Some(topics) = topics.iter().map(Value::as_str).collect::<Option<Vec<_>>>() else {
+                continue;
+            };
+
+            if decode(topics[0])?["symbol"] != EXECUTABLE_UPDATE_TOPIC {
+                continue;
+            }
+
+            events.push(UpgradeEvent {
+                ledger: as_u64(&event["ledger"]).unwrap_or(0),
+                old_hash: executable_hash(&decode(topics[1])?),
+                new_hash: executable_hash(&decode(topics[2])?),
+            });
+        }
+    }
+
+    Ok(events)
+}
+
+/// WASM hash replaced by the last upgrade to the current WASM
+pub fn previous_wasm_hash(events: &[UpgradeEvent], current_hash: &str) -> Option<String> {
+    events
+        .iter()
+        .filter(|event| event.new_hash.as_deref() == Some(current_hash))
+        .max_by_key(|event| event.ledger)
+        .and_then(|event| event.old_hash.clone())
+}
+
+/// Fetch the executable updates of the contract still retained by the RPC
+fn fetch_upgrade_events(
+    contract_id: &str,
+    network_flags: &str,
+    latest_ledger: u64,
+) -> Result<Vec<UpgradeEvent>, String> {
+    let start_ledger = latest_ledger.saturating_sub(EVENT_RETENTION_LEDGERS).max(1);
+    let command = format!(
+        "stellar events --id {} --start-ledger {} --count {} {} --output json",
+        contract_id, start_ledger, EVENT_COUNT, network_flags
+    );
+    let output =
+        capture_command(&command).map_err(|e| format!("Failed to fetch contract events: {}", e))?;
+
+    parse_upgrade_events(&output, decode_scval)
+}
+
+/// Upgrade arguments restoring `wasm_hash`
+pub fn to_upgrade_args(args: &RollbackArgs, wasm_hash: &str) -> Result<UpgradeArgs, String> {
+    let mut argv = vec![
+        "upgrade".to_string(),
+        "--id".to_string(),
+        args.id.clone(),
+        "--wasm-hash".to_string(),
+        wasm_hash.to_string(),
+        "--source".to_string(),
+        args.source.clone(),
+        "--network".to_string(),
+        args.network.clone(),
+    ];
+    argv.extend(args.args.iter().cloned());
+
+    let upgrade =
+        UpgradeArgs::try_parse_from(argv).map_err(|e| e.to_string().trim().to_string())?;
+    Ok(UpgradeArgs {
+        rollback: true,
+        ..upgrade
+    })
+}
+
+/// Network options of the rollback lookups, including those passed on to the upgrade
+fn lookup_network_flags(args: &RollbackArgs) -> Result<String, String> {
+    // The target WASM isn't known yet and doesn't affect the network options
+    to_upgrade_args(args, &"0".repeat(64)).map(|upgrade| network_flags(&upgrade))
+}
+
+/// Run the rollback command, upgrading the contract back to its previous WASM
+pub fn run_rollback(args: &RollbackArgs) -> Result<(), String> {
+    let (id, _) = contract_id::resolve_contract_id(
+        &args.id,
+        &args.network,
+        &Config::load()?,
+        contract_id::show_cli_alias,
+    )?;
+    let network_flags = lookup_network_flags(args)?;
+
+    let instance = ledger::fetch_contract_instance(&id, &network_flags)?;
+    let current_hash = ledger::extract_executable_hash(&instance)?;
+
+    let recorded = history::previous_wasm_hash(
+        &history::read(&history::history_path()?)?,
+        &id,
+        &args.network,
+        &current_hash,
+    );
+
+    let target = match (&args.to, recorded) {
+        (Some(hash), _) => hash.clone(),
+        (None, Some(hash)) => {
+            println!("Found the previous WASM in the upgrade history");
+            hash
+        }
+        (None, None) => {
+            let latest_ledger = ledger::extract_latest_ledger(&instance)?;
+            let events = fetch_upgrade_events(&id, &network_flags, latest_ledger)?;
+            previous_wasm_hash(&events, &current_hash).ok_or_else(|| {
+                format!(
+                    "❌ No upgrade to WASM {} found in the history nor in the events retained by the RPC, pass the WASM to restore with --to",
+                    current_hash
+                )
+            })?
+        }
+    };
+
+    if target == current_hash {
+        return Err(format!(
+            "❌ The contract already runs WASM {}, nothing to roll back",
+            target
+        ));
+    }
+
+    println!(
+        "Rolling back from WASM {} to WASM {}\n",
+        current_hash, target
+    );
+    run_upgrade(&to_upgrade_args(args, &target)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_HASH: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    const NEW_HASH: &str = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+
+    fn create_test_args() -> RollbackArgs {
+        RollbackArgs {
+            id: "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM".to_string(),
+            to: None,
+            source: "alice".to_string(),
+            network: "testnet".to_string(),
+            args: vec![],
+        }
+    }
+
+    /// Decoder standing in for `stellar xdr decode`, topics being named by their content
+    fn fake_decode(xdr: &str) -> Result<Value, String> {
+        Ok(match xdr {
+            "update" => serde_json::json!({ "symbol": "executable_update" }),
+            "transfer" => serde_json::json!({ "symbol": "transfer" }),
+            "old" => serde_json::json!({ "vec": [{ "symbol": "Wasm" }, { "bytes": OLD_HASH }] }),
+            "new" => serde_json::json!({ "vec": [{ "symbol": "Wasm" }, { "bytes": NEW_HASH }] }),
+            other => serde_json::json!({ "string": other }),
+        })
+    }
+
+    #[test]
+    fn test_parse_upgrade_events() {
+        let output = r#"
+            {"type":"contract","ledger":120,"topic":["update","old","new"],"value":"AAAAAQ=="}
+            {"type":"contract","ledger":130,"topic":["transfer","from","to"],"value":"AAAAAQ=="}
+            {"type":"contract","ledger":140,"topic":["mint"],"value":"AAAAAQ=="}
+        "#;
+
+        let events = parse_upgrade_events(output, fake_decode).unwrap();
+        assert_eq!(
+            events,
+            vec![UpgradeEvent {
+                ledger: 120,
+                old_hash: Some(OLD_HASH.to_string()),
+                new_hash: Some(NEW_HASH.to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_upgrade_events_array() {
+        let output = r#"[{"ledger":"120","topic":["update","old","new"]}]"#;
+        assert_eq!(parse_upgrade_events(output, fake_decode).unwrap().len(), 1);
+
+        assert!(parse_upgrade_events("not json", fake_decode).is_err());
+    }
+
+    #[test]
+    fn test_previous_wasm_hash() {
+        let events = vec![
+            UpgradeEvent {
+                ledger: 100,
+                old_hash: Some("a".repeat(64)),
+                new_hash: Some(NEW_HASH.to_string()),
+            },
+            UpgradeEvent {
+                ledger: 200,
+                old_hash: Some(NEW_HASH.to_string()),
+                new_hash: Some(OLD_HASH.to_string()),
+            },
+            UpgradeEvent {
+                ledger: 300,
+                old_hash: Some("b".repeat(64)),
+                new_hash: Some(NEW_HASH.to_string()),
+            },
+        ];
+
+        assert_eq!(previous_wasm_hash(&events, NEW_HASH), Some("b".repeat(64)));
+        assert_eq!(
+            previous_wasm_hash(&events, OLD_HASH),
+            Some(NEW_HASH.to_string())
+        );
+        assert_eq!(previous_wasm_hash(&events, &"c".repeat(64)), None);
+    }
+
+    #[test]
+    fn test_to_upgrade_args() {
+        let mut args = create_test_args();
+        args.args = vec!["--upgrade-fn".to_string(), "set_code".to_string()];
+
+        let upgrade = to_upgrade_args(&args, OLD_HASH).unwrap();
+        assert_eq!(upgrade.id, args.id);
+        assert_eq!(upgrade.wasm_hash, OLD_HASH);
+        assert_eq!(upgrade.upgrade_fn.as_deref(), Some("set_code"));
+        assert!(upgrade.rollback);
+
+        args.args = vec!["--unknown".to_string()];
+        assert!(to_upgrade_args(&args, OLD_HASH).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use super::contract_info::{self, ContractFunction, FunctionParam};
 use super::{SecurityCheck, SecurityCheckContext};
 use crate::UpgradeArgs;
 
@@ -7,6 +8,26 @@ impl ConstructorCheck {
     pub fn new() -> Self {
         ConstructorCheck
     }
+
+    /// Find the `__constructor` function of a contract interface
+    fn find_constructor(&self, interface: &str) -> Option<ContractFunction> {
+        contract_info::parse_functions(interface)
+            .into_iter()
+            .find(|function| function.name == "__constructor")
+    }
+
+    /// Constructor parameters of the new code that the current constructor didn't take
+    pub fn unapplied_params(
+        &self,
+        current: Option<&ContractFunction>,
+        new: &ContractFunction,
+    ) -> Vec<FunctionParam> {
+        new.params
+            .iter()
+            .filter(|param| current.is_none_or(|current| !current.params.contains(param)))
+            .cloned()
+            .collect()
+    }
 }
 
 impl SecurityCheck for ConstructorCheck {
@@ -14,14 +35,61 @@ impl SecurityCheck for ConstructorCheck {
         "Constructor Check"
     }
 
-    fn run(&self, _args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
+    fn run(&self, args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
         if let Some(interface) = &context.contract_interface {
-            // Check if the interface contains a __constructor function
-            if !interface.contains("fn __constructor(") {
-                println!("✅ Contract does not have a __constructor function");
+            let new_constructor = match self.find_constructor(interface) {
+                Some(constructor) => constructor,
+                None => {
+                    println!("✅ Contract does not have a __constructor function");
+                    return Ok(());
+                }
+            };
+            let current_constructor = context
+                .current_interface
+                .as_deref()
+                .and_then(|interface| self.find_constructor(interface));
+
+            // Constructors don't run on upgrade: only a constructor that already ran when
+            // the contract was deployed is safe
+            if current_constructor.as_ref() == Some(&new_constructor) {
+                println!("✅ Contract __constructor is unchanged and already ran at deployment");
+                return Ok(());
+            }
+
+            let change = if current_constructor.is_some() {
+                "changes"
+            } else {
+                "adds"
+            };
+            let unapplied = self.unapplied_params(current_constructor.as_ref(), &new_constructor);
+            println!(
+                "⚠️  New code {} a __constructor function, which won't run on upgrade.",
+                change
+            );
+            if unapplied.is_empty() {
+                println!("⚠️  State it initializes differently won't be set up.");
+            } else {
+                println!("⚠️  These constructor arguments won't be applied:");
+                for param in &unapplied {
+                    println!("⚠️    - {}: {}", param.name, param.type_name);
+                }
+            }
+
+            if args.ack_constructor {
+                println!("✅ Constructor change acknowledged");
+                Ok(())
+            } else if let Some(migrate) = &args.migrate {
+                println!(
+                    "✅ State will be initialized by the migration function `{}`",
+                    migrate
+                );
                 Ok(())
             } else {
-                Err("❌ Contract has a __constructor function, which might cause issues during upgrade.".to_string())
+                Err(format!(
+                    "❌ New code {} a __constructor function whose state won't be initialized by the upgrade. \
+                    Pass --ack-constructor to acknowledge, or --migrate to initialize it in a migration function.",
+                    change
+                ))
             }
         } else {
             Err("Contract interface information not available".to_string())
@@ -56,17 +124,7 @@ mod tests {
                 wasm_hash: "test".to_string(),
                 source: "test".to_string(),
                 network: "test".to_string(),
-                rpc_url: None,
-                rpc_header: None,
-                network_passphrase: None,
-                fee: 100,
-                is_view: false,
-                instructions: None,
-                build_only: false,
-                send: None,
-                cost: false,
-                force: false,
-                contract_args: vec![],
+                ..UpgradeArgs::for_test()
             },
             &mut context,
         );
@@ -98,21 +156,97 @@ mod tests {
                 wasm_hash: "test".to_string(),
                 source: "test".to_string(),
                 network: "test".to_string(),
-                rpc_url: None,
-                rpc_header: None,
-                network_passphrase: None,
-                fee: 100,
-                is_view: false,
-                instructions: None,
-                build_only: false,
-                send: None,
-                cost: false,
-                force: false,
-                contract_args: vec![],
+                ..UpgradeArgs::for_test()
             },
             &mut context,
         );
 
         assert!(result.is_err());
     }
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test".to_string(),
+            wasm_hash: "test".to_string(),
+            source: "test".to_string(),
+            network: "test".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    fn create_context(
+        current_constructor: Option<&str>,
+        new_constructor: &str,
+    ) -> SecurityCheckContext {
+        let mut context = SecurityCheckContext::new();
+        context.current_interface = current_constructor
+            .map(|constructor| format!("pub trait Contract {{\n    {}\n}}", constructor));
+        context.contract_interface = Some(format!(
+            "pub trait Contract {{\n    {}\n}}",
+            new_constructor
+        ));
+        context
+    }
+
+    #[test]
+    fn test_constructor_check_unchanged_constructor() {
+        let constructor = "fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address);";
+        let mut context = create_context(Some(constructor), constructor);
+
+        let check = ConstructorCheck::new();
+        assert!(check.run(&create_test_args(), &mut context).is_ok());
+    }
+
+    #[test]
+    fn test_constructor_check_changed_constructor() {
+        let mut context = create_context(
+            Some("fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address);"),
+            "fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address, fee: u32);",
+        );
+
+        let check = ConstructorCheck::new();
+        let result = check.run(&create_test_args(), &mut context);
+        assert!(result
+            .unwrap_err()
+            .contains("changes a __constructor function"));
+    }
+
+    #[test]
+    fn test_constructor_check_acknowledged() {
+        let check = ConstructorCheck::new();
+
+        let mut args = create_test_args();
+        args.ack_constructor = true;
+        let mut context = create_context(
+            None,
+            "fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address);",
+        );
+        assert!(check.run(&args, &mut context).is_ok());
+
+        let mut args = create_test_args();
+        args.migrate = Some("initialize".to_string());
+        let mut context = create_context(
+            None,
+            "fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address);",
+        );
+        assert!(check.run(&args, &mut context).is_ok());
+    }
+
+    #[test]
+    fn test_unapplied_params() {
+        let check = ConstructorCheck::new();
+        let current = contract_info::parse_functions(
+            "fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address);",
+        );
+        let new = contract_info::parse_functions(
+            "fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address, fee: u32);",
+        );
+
+        let unapplied = check.unapplied_params(Some(&current[0]), &new[0]);
+        assert_eq!(unapplied.len(), 1);
+        assert_eq!(unapplied[0].name, "fee");
+
+        let unapplied = check.unapplied_params(None, &new[0]);
+        assert_eq!(unapplied.len(), 2);
+    }
 }
@@ -1,5 +1,6 @@
 use super::SecurityCheckContext;
-use crate::UpgradeArgs;
+use crate::{network_flags, wasm, UpgradeArgs};
+use regex::Regex;
 use std::process::Command;
 
 pub fn fetch_contract_interface(
@@ -10,8 +11,8 @@ pub fn fetch_contract_interface(
 
     // Construct the command to get contract interface
     let command = format!(
-        "stellar contract info interface --wasm-hash {} --network {}",
-        args.wasm_hash, args.network
+        "stellar contract info interface {}",
+        wasm::code_source(args, &args.wasm_hash)
     );
 
     // Execute the command
@@ -39,3 +40,626 @@ pub fn fetch_contract_interface(
         Err(e) => Err(format!("Failed to execute command: {}", e)),
     }
 }
+
+/// Fetch the interface of the contract currently deployed at `--id`
+pub fn fetch_deployed_interface(args: &UpgradeArgs) -> Result<String, String> {
+    let command = format!(
+        "stellar contract info interface --id {} {}",
+        args.id,
+        network_flags(args)
+    );
+
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", &command]).output()
+    } else {
+        Command::new("sh").args(["-c", &command]).output()
+    };
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                String::from_utf8(output.stdout)
+                    .map_err(|_| "Failed to parse contract interface output".to_string())
+            } else if let Ok(stderr) = String::from_utf8(output.stderr) {
+                Err(format!("Failed to get contract interface: {}", stderr))
+            } else {
+                Err("Failed to get contract interface".to_string())
+            }
+        }
+        Err(e) => Err(format!("Failed to execute command: {}", e)),
+    }
+}
+
+/// A parameter of a contract function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionParam {
+    pub name: String,
+    pub type_name: String,
+}
+
+impl FunctionParam {
+    /// Whether the parameter can hold a WASM hash
+    pub fn is_wasm_hash(&self) -> bool {
+        self.type_name.replace(' ', "").ends_with("BytesN<32>")
+    }
+}
+
+/// A function exported by a contract, without its `env` parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractFunction {
+    pub name: String,
+    pub params: Vec<FunctionParam>,
+}
+
+/// The function used to upgrade a contract
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeEntrypoint {
+    pub function: String,
+    pub hash_param: String,
+    /// Parameters besides the WASM hash that must be supplied
+    pub extra_params: Vec<FunctionParam>,
+    /// Every parameter, the WASM hash included, in declaration order
+    pub params: Vec<FunctionParam>,
+}
+
+impl UpgradeEntrypoint {
+    /// Extra parameters for which no value was provided
+    pub fn missing_params(&self, provided: &[(String, String)]) -> Vec<FunctionParam> {
+        self.extra_params
+            .iter()
+            .filter(|param| !provided.iter().any(|(name, _)| name == &param.name))
+            .cloned()
+            .collect()
+    }
+
+    /// Signature of the function, e.g. `upgrade(new_wasm_hash: BytesN<32>, operator: Address)`
+    pub fn signature(&self) -> String {
+        format!(
+            "{}({}: BytesN<32>{})",
+            self.function,
+            self.hash_param,
+            self.extra_params
+                .iter()
+                .map(|param| format!(", {}: {}", param.name, param.type_name))
+                .collect::<String>()
+        )
+    }
+}
+
+/// Split a parameter list on the commas that are not nested in generics or tuples
+fn split_params(params: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in params.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&params[start..]);
+
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Parse the functions declared in a contract interface
+pub fn parse_functions(interface: &str) -> Vec<ContractFunction> {
+    let signature = Regex::new(r"fn (\w+)\(").unwrap();
+    let mut functions = Vec::new();
+
+    for captures in signature.captures_iter(interface) {
+        let start = captures.get(0).unwrap().end();
+
+        // Find the parenthesis closing the parameter list
+        let mut depth = 1;
+        let mut end = None;
+        for (i, c) in interface[start..].char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(start + i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(end) = end else { continue };
+
+        let params = split_params(&interface[start..end])
+            .into_iter()
+            .filter_map(|param| param.split_once(':'))
+            .map(|(name, type_name)| FunctionParam {
+                name: name.trim().to_string(),
+                type_name: type_name.trim().to_string(),
+            })
+            .filter(|param| !param.type_name.ends_with("Env"))
+            .collect();
+
+        functions.push(ContractFunction {
+            name: captures[1].to_string(),
+            params,
+        });
+    }
+
+    functions
+}
+
+/// Find the upgrade entrypoint, either by name or by its single `BytesN<32>` parameter
+///
+/// Without `function_name`, only a function named `upgrade` is accepted: another function
+/// taking a WASM hash is named in the error, to be passed explicitly with `--upgrade-fn`.
+pub fn find_upgrade_entrypoint(
+    functions: &[ContractFunction],
+    function_name: Option<&str>,
+    hash_param: Option<&str>,
+) -> Result<UpgradeEntrypoint, String> {
+    let mut candidates: Vec<UpgradeEntrypoint> = functions
+        .iter()
+        .filter(|function| function.name != "__constructor")
+        .filter(|function| function_name.is_none_or(|name| function.name == name))
+        .filter_map(|function| {
+            let hashes: Vec<&FunctionParam> = function
+                .params
+                .iter()
+                .filter(|param| param.is_wasm_hash())
+                .filter(|param| hash_param.is_none_or(|name| param.name == name))
+                .collect();
+
+            match hashes.as_slice() {
+                [hash] => Some(UpgradeEntrypoint {
+                    function: function.name.clone(),
+                    hash_param: hash.name.clone(),
+                    extra_params: function
+                        .params
+                        .iter()
+                        .filter(|param| param.name != hash.name)
+                        .cloned()
+                        .collect(),
+                    params: function.params.clone(),
+                }),
+                _ => None,
+            }
+        })
+        .collect();
+
+    // Prefer the conventional name when several functions take a single WASM hash
+    if candidates.len() > 1 {
+        if let Some(index) = candidates.iter().position(|c| c.function == "upgrade") {
+            return Ok(candidates.swap_remove(index));
+        }
+    }
+
+    match candidates.len() {
+        0 => Err(match (function_name, hash_param) {
+            (Some(name), Some(param)) => format!(
+                "No function `{}` with a `{}: BytesN<32>` parameter found",
+                name, param
+            ),
+            (Some(name), None) => format!(
+                "No function `{}` with a single `BytesN<32>` parameter found",
+                name
+            ),
+            _ => "No function with a single `BytesN<32>` parameter found".to_string(),
+        }),
+        1 if function_name.is_none() && candidates[0].function != "upgrade" => Err(format!(
+            "Function `{}` takes a WASM hash but isn't named `upgrade`, pass --upgrade-fn {} to use it as the upgrade entrypoint",
+            candidates[0].function, candidates[0].function
+        )),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(format!(
+            "Several functions could be the upgrade entrypoint ({}), use --upgrade-fn to pick one",
+            candidates
+                .iter()
+                .map(|c| c.function.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// A variant of a `#[contracttype]` enum
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    /// Types of the tuple fields, empty for unit variants
+    pub fields: Vec<String>,
+    /// Discriminant of integer enums
+    pub value: Option<u32>,
+}
+
+/// A `#[contracttype]` enum declared in a contract interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractEnum {
+    pub name: String,
+    /// Doc comment lines, without the `///` prefix
+    pub doc: Vec<String>,
+    pub variants: Vec<EnumVariant>,
+}
+
+/// Parse the `#[contracttype]` enums declared in a contract interface
+pub fn parse_enums(interface: &str) -> Vec<ContractEnum> {
+    let contract_enum = Regex::new(
+        r"((?:[ \t]*///[^\n]*\n)*)[ \t]*#\[soroban_sdk::contracttype[^\n]*\n(?:\s*#\[[^\n]*\n)*\s*pub enum (\w+)\s*\{([^}]*)\}",
+    )
+    .unwrap();
+    let variant = Regex::new(r"^(\w+)\s*(?:\((.*)\))?\s*(?:=\s*(\d+))?$").unwrap();
+
+    contract_enum
+        .captures_iter(interface)
+        .map(|captures| {
+            let doc = captures[1]
+                .lines()
+                .map(|line| line.trim().trim_start_matches("///").trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect();
+
+            // Drop the doc comments of the variants before splitting them
+            let body: String = captures[3]
+                .lines()
+                .filter(|line| !line.trim().starts_with("//"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let variants = split_params(&body)
+                .into_iter()
+                .filter_map(|declaration| variant.captures(declaration))
+                .map(|variant| EnumVariant {
+                    name: variant[1].to_string(),
+                    fields: variant
+                        .get(2)
+                        .map(|fields| {
+                            split_params(fields.as_str())
+                                .into_iter()
+                                .map(str::to_string)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    value: variant.get(3).and_then(|value| value.as_str().parse().ok()),
+                })
+                .collect();
+
+            ContractEnum {
+                name: captures[2].to_string(),
+                doc,
+                variants,
+            }
+        })
+        .collect()
+}
+
+/// A `#[contracttype]` struct with named fields declared in a contract interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractStruct {
+    pub name: String,
+    pub fields: Vec<FunctionParam>,
+}
+
+/// Parse the `#[contracttype]` structs with named fields declared in a contract interface
+pub fn parse_structs(interface: &str) -> Vec<ContractStruct> {
+    let contract_struct = Regex::new(
+        r"#\[soroban_sdk::contracttype[^\n]*\n(?:\s*#\[[^\n]*\n)*\s*pub struct (\w+)\s*\{([^}]*)\}",
+    )
+    .unwrap();
+
+    contract_struct
+        .captures_iter(interface)
+        .map(|captures| {
+            let body: String = captures[2]
+                .lines()
+                .filter(|line| !line.trim().starts_with("//"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let fields = split_params(&body)
+                .into_iter()
+                .filter_map(|field| {
+                    let (name, type_name) = field.trim_start_matches("pub ").split_once(':')?;
+                    Some(FunctionParam {
+                        name: name.trim().to_string(),
+                        type_name: type_name.trim().to_string(),
+                    })
+                })
+                .collect();
+
+            ContractStruct {
+                name: captures[1].to_string(),
+                fields,
+            }
+        })
+        .collect()
+}
+
+/// A variant of a `#[contracterror]` enum
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractError {
+    pub enum_name: String,
+    pub variant: String,
+    pub code: u32,
+}
+
+/// Parse the contract error enums declared in a contract interface
+pub fn parse_contract_errors(interface: &str) -> Vec<ContractError> {
+    let error_enum =
+        Regex::new(r"contracterror[^\n]*\n(?:\s*#\[[^\n]*\n)*\s*pub enum (\w+)\s*\{([^}]*)\}")
+            .unwrap();
+    let variant = Regex::new(r"(\w+)\s*=\s*(\d+)").unwrap();
+
+    let mut errors = Vec::new();
+    for captures in error_enum.captures_iter(interface) {
+        for variant_captures in variant.captures_iter(&captures[2]) {
+            if let Ok(code) = variant_captures[2].parse() {
+                errors.push(ContractError {
+                    enum_name: captures[1].to_string(),
+                    variant: variant_captures[1].to_string(),
+                    code,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERFACE: &str = r#"
+        #[soroban_sdk::contractargs(name = "Args")]
+        #[soroban_sdk::contractclient(name = "Client")]
+        pub trait Contract {
+            fn __constructor(env: soroban_sdk::Env, admin: soroban_sdk::Address);
+            fn version(env: soroban_sdk::Env) -> u32;
+            fn upgrade(
+                env: soroban_sdk::Env,
+                new_wasm_hash: soroban_sdk::BytesN<32>,
+                operator: soroban_sdk::Address,
+            );
+            fn set_range(env: soroban_sdk::Env, range: (u32, u32), values: soroban_sdk::Map<u32, u32>);
+        }
+        "#;
+
+    fn param(name: &str, type_name: &str) -> FunctionParam {
+        FunctionParam {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_functions() {
+        let functions = parse_functions(INTERFACE);
+
+        assert_eq!(functions.len(), 4);
+        assert_eq!(functions[0].name, "__constructor");
+        assert_eq!(functions[1].name, "version");
+        assert!(functions[1].params.is_empty());
+        assert_eq!(
+            functions[2].params,
+            vec![
+                param("new_wasm_hash", "soroban_sdk::BytesN<32>"),
+                param("operator", "soroban_sdk::Address"),
+            ]
+        );
+        assert_eq!(
+            functions[3].params,
+            vec![
+                param("range", "(u32, u32)"),
+                param("values", "soroban_sdk::Map<u32, u32>"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_upgrade_entrypoint_detected() {
+        let functions = parse_functions(INTERFACE);
+
+        let entrypoint = find_upgrade_entrypoint(&functions, None, None).unwrap();
+        assert_eq!(entrypoint.function, "upgrade");
+        assert_eq!(entrypoint.hash_param, "new_wasm_hash");
+        assert_eq!(
+            entrypoint.extra_params,
+            vec![param("operator", "soroban_sdk::Address")]
+        );
+    }
+
+    #[test]
+    fn test_find_upgrade_entrypoint_custom_name() {
+        let functions = parse_functions(
+            "fn migrate_code(env: soroban_sdk::Env, hash: soroban_sdk::BytesN<32>);",
+        );
+
+        // A detected function not named `upgrade` must be picked explicitly
+        let err = find_upgrade_entrypoint(&functions, None, None).unwrap_err();
+        assert!(err.contains("--upgrade-fn migrate_code"));
+
+        let entrypoint =
+            find_upgrade_entrypoint(&functions, Some("migrate_code"), Some("hash")).unwrap();
+        assert_eq!(entrypoint.function, "migrate_code");
+        assert_eq!(entrypoint.hash_param, "hash");
+
+        assert!(find_upgrade_entrypoint(&functions, Some("upgrade"), None).is_err());
+        assert!(find_upgrade_entrypoint(&functions, None, Some("new_wasm_hash")).is_err());
+    }
+
+    #[test]
+    fn test_find_upgrade_entrypoint_ambiguous() {
+        let functions = parse_functions(
+            r#"
+            fn set_code(env: soroban_sdk::Env, hash: soroban_sdk::BytesN<32>);
+            fn replace(env: soroban_sdk::Env, hash: soroban_sdk::BytesN<32>);
+            "#,
+        );
+
+        let result = find_upgrade_entrypoint(&functions, None, None);
+        assert!(result.unwrap_err().contains("set_code, replace"));
+
+        let entrypoint = find_upgrade_entrypoint(&functions, Some("replace"), None).unwrap();
+        assert_eq!(entrypoint.function, "replace");
+    }
+
+    #[test]
+    fn test_missing_params() {
+        let functions = parse_functions(INTERFACE);
+        let entrypoint = find_upgrade_entrypoint(&functions, None, None).unwrap();
+
+        assert_eq!(entrypoint.missing_params(&[]).len(), 1);
+        assert!(entrypoint
+            .missing_params(&[("operator".to_string(), "GABC".to_string())])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_structs() {
+        let interface = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+        pub struct Config {
+            /// The administrator
+            pub admin: soroban_sdk::Address,
+            pub limits: soroban_sdk::Map<u32, u32>,
+        }
+
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum DataKey {
+            Config,
+        }
+        "#;
+
+        let structs = parse_structs(interface);
+        assert_eq!(
+            structs,
+            vec![ContractStruct {
+                name: "Config".to_string(),
+                fields: vec![
+                    param("admin", "soroban_sdk::Address"),
+                    param("limits", "soroban_sdk::Map<u32, u32>"),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_enums() {
+        let interface = r#"
+        /// Keys of the persistent storage
+        /// @storage-key
+        #[soroban_sdk::contracttype(export = false)]
+        #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+        pub enum DataKey {
+            /// The administrator
+            Admin,
+            Balance(soroban_sdk::Address),
+            Allowance(soroban_sdk::Address, soroban_sdk::Map<u32, u32>),
+        }
+
+        #[soroban_sdk::contracttype(export = false)]
+        #[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+        pub enum Role {
+            Owner = 0,
+            Operator = 1,
+        }
+
+        #[soroban_sdk::contracterror(export = false)]
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+        pub enum Error {
+            NotAuthorized = 1,
+        }
+        "#;
+
+        let enums = parse_enums(interface);
+        assert_eq!(enums.len(), 2);
+
+        assert_eq!(enums[0].name, "DataKey");
+        assert_eq!(
+            enums[0].doc,
+            vec!["Keys of the persistent storage", "@storage-key"]
+        );
+        assert_eq!(
+            enums[0].variants,
+            vec![
+                EnumVariant {
+                    name: "Admin".to_string(),
+                    fields: vec![],
+                    value: None,
+                },
+                EnumVariant {
+                    name: "Balance".to_string(),
+                    fields: vec!["soroban_sdk::Address".to_string()],
+                    value: None,
+                },
+                EnumVariant {
+                    name: "Allowance".to_string(),
+                    fields: vec![
+                        "soroban_sdk::Address".to_string(),
+                        "soroban_sdk::Map<u32, u32>".to_string()
+                    ],
+                    value: None,
+                },
+            ]
+        );
+
+        assert_eq!(enums[1].name, "Role");
+        assert!(enums[1].doc.is_empty());
+        assert_eq!(enums[1].variants[1].value, Some(1));
+    }
+
+    #[test]
+    fn test_parse_contract_errors() {
+        let interface = r#"
+        #[soroban_sdk::contracterror(export = false)]
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+        pub enum Error {
+            NotAuthorized = 1,
+            AlreadyInitialized = 2,
+        }
+
+        #[soroban_sdk::contracttype(export = false)]
+        #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+        pub enum DataKey {
+            Admin,
+        }
+        "#;
+
+        let errors = parse_contract_errors(interface);
+        assert_eq!(
+            errors,
+            vec![
+                ContractError {
+                    enum_name: "Error".to_string(),
+                    variant: "NotAuthorized".to_string(),
+                    code: 1,
+                },
+                ContractError {
+                    enum_name: "Error".to_string(),
+                    variant: "AlreadyInitialized".to_string(),
+                    code: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_contract_errors_none() {
+        let interface = r#"
+        pub trait Contract {
+            fn version(env: soroban_sdk::Env) -> u32;
+        }
+        "#;
+
+        assert!(parse_contract_errors(interface).is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use super::{contract_info, SecurityCheck, SecurityCheckContext};
+use crate::UpgradeArgs;
+
+pub struct MigrationFunctionCheck;
+
+impl MigrationFunctionCheck {
+    pub fn new() -> Self {
+        MigrationFunctionCheck
+    }
+}
+
+impl SecurityCheck for MigrationFunctionCheck {
+    fn name(&self) -> &str {
+        "Migration Function Check"
+    }
+
+    fn run(&self, args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
+        let migrate = match &args.migrate {
+            Some(migrate) => migrate,
+            None => {
+                println!("✅ No migration requested");
+                return Ok(());
+            }
+        };
+
+        if let Some(interface) = &context.contract_interface {
+            let functions = contract_info::parse_functions(interface);
+            let function = functions
+                .iter()
+                .find(|function| &function.name == migrate)
+                .ok_or_else(|| {
+                    format!(
+                        "❌ New contract does not export the migration function `{}`",
+                        migrate
+                    )
+                })?;
+
+            // Every parameter must be supplied, and every supplied argument must exist
+            let missing: Vec<&str> = function
+                .params
+                .iter()
+                .filter(|param| {
+                    !args
+                        .migrate_args
+                        .iter()
+                        .any(|(name, _)| name == &param.name)
+                })
+                .map(|param| param.name.as_str())
+                .collect();
+            let unknown: Vec<&str> = args
+                .migrate_args
+                .iter()
+                .filter(|(name, _)| !function.params.iter().any(|param| &param.name == name))
+                .map(|(name, _)| name.as_str())
+                .collect();
+
+            if !missing.is_empty() || !unknown.is_empty() {
+                return Err(format!(
+                    "❌ Migration function `{}({})` does not match the provided arguments (missing: [{}], unknown: [{}])",
+                    migrate,
+                    function
+                        .params
+                        .iter()
+                        .map(|param| format!("{}: {}", param.name, param.type_name))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    missing.join(", "),
+                    unknown.join(", ")
+                ));
+            }
+
+            println!(
+                "✅ New contract exports the migration function `{}` with the expected signature",
+                migrate
+            );
+            Ok(())
+        } else {
+            Err("Contract interface information not available".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_context() -> SecurityCheckContext {
+        let mut context = SecurityCheckContext::new();
+        context.contract_interface = Some(
+            r#"
+        #[soroban_sdk::contractargs(name = "Args")]
+        #[soroban_sdk::contractclient(name = "Client")]
+        pub trait Contract {
+            fn handle_upgrade(env: soroban_sdk::Env);
+            fn migrate(env: soroban_sdk::Env, version: u32);
+            fn version(env: soroban_sdk::Env) -> u32;
+            fn upgrade(env: soroban_sdk::Env, new_wasm_hash: soroban_sdk::BytesN<32>);
+        }
+        "#
+            .to_string(),
+        );
+        context
+    }
+
+    fn create_test_args(migrate: Option<&str>, migrate_args: &[(&str, &str)]) -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test".to_string(),
+            wasm_hash: "test".to_string(),
+            source: "test".to_string(),
+            network: "test".to_string(),
+            migrate: migrate.map(str::to_string),
+            migrate_args: migrate_args
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    #[test]
+    fn test_migration_function_check_no_migration() {
+        let check = MigrationFunctionCheck::new();
+        let result = check.run(&create_test_args(None, &[]), &mut create_test_context());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_migration_function_check_pass() {
+        let check = MigrationFunctionCheck::new();
+
+        let result = check.run(
+            &create_test_args(Some("handle_upgrade"), &[]),
+            &mut create_test_context(),
+        );
+        assert!(result.is_ok());
+
+        let result = check.run(
+            &create_test_args(Some("migrate"), &[("version", "2")]),
+            &mut create_test_context(),
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_migration_function_check_missing_function() {
+        let check = MigrationFunctionCheck::new();
+        let result = check.run(
+            &create_test_args(Some("migrate_v2"), &[]),
+            &mut create_test_context(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_migration_function_check_wrong_arguments() {
+        let check = MigrationFunctionCheck::new();
+
+        let result = check.run(
+            &create_test_args(Some("migrate"), &[]),
+            &mut create_test_context(),
+        );
+        assert!(result.unwrap_err().contains("missing: [version]"));
+
+        let result = check.run(
+            &create_test_args(Some("handle_upgrade"), &[("version", "2")]),
+            &mut create_test_context(),
+        );
+        assert!(result.unwrap_err().contains("unknown: [version]"));
+    }
+}
@@ -1,20 +1,34 @@
 mod constructor_check;
-mod contract_info;
+pub(crate) mod contract_info;
+mod migration_function_check;
+mod protocol_check;
+mod storage_key_check;
+mod storage_snapshot_check;
 #[cfg(test)]
 mod tests;
+mod ttl_check;
+mod unprotected_upgrade_check;
 mod upgrade_function_check;
-mod version_check;
+pub(crate) mod version_check;
+mod wasm_installed_check;
 
 use crate::UpgradeArgs;
+use contract_info::UpgradeEntrypoint;
 
 pub struct SecurityCheckContext {
     pub contract_interface: Option<String>,
+    /// Interface of the contract currently deployed
+    pub current_interface: Option<String>,
+    /// Upgrade entrypoint of the new code, detected by the upgrade function check
+    pub upgrade_entrypoint: Option<UpgradeEntrypoint>,
 }
 
 impl SecurityCheckContext {
     pub fn new() -> Self {
         SecurityCheckContext {
             contract_interface: None,
+            current_interface: None,
+            upgrade_entrypoint: None,
         }
     }
 }
@@ -24,28 +38,78 @@ pub trait SecurityCheck {
     fn run(&self, args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String>;
 }
 
+/// Name of a check for --skip-check, e.g. `unprotected-upgrade` for the Unprotected Upgrade Check
+pub fn check_id(name: &str) -> String {
+    name.trim_end_matches(" Check")
+        .to_lowercase()
+        .replace(' ', "-")
+}
+
+/// Refuse --skip-check names that don't match a security check
+fn validate_skipped_checks(args: &UpgradeArgs) -> Result<(), String> {
+    let ids: Vec<String> = get_security_checks()
+        .iter()
+        .map(|check| check_id(check.name()))
+        .collect();
+
+    match args
+        .skip_checks
+        .iter()
+        .find(|skipped| !ids.contains(skipped))
+    {
+        Some(unknown) => Err(format!(
+            "Unknown security check `{}` in --skip-check, expected one of: {}",
+            unknown,
+            ids.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
 // Register all security checks here
 pub fn get_security_checks() -> Vec<Box<dyn SecurityCheck>> {
     vec![
         Box::new(constructor_check::ConstructorCheck::new()),
         Box::new(upgrade_function_check::UpgradeFunctionCheck::new()),
+        Box::new(unprotected_upgrade_check::UnprotectedUpgradeCheck::new()),
+        Box::new(migration_function_check::MigrationFunctionCheck::new()),
         Box::new(version_check::VersionCheck::new()),
+        Box::new(protocol_check::ProtocolCheck::new()),
+        Box::new(storage_key_check::StorageKeyCheck::new()),
+        Box::new(storage_snapshot_check::StorageSnapshotCheck::new()),
+        Box::new(ttl_check::TtlCheck::new()),
     ]
 }
 
-// Run all security checks
-pub fn run_all_checks(args: &UpgradeArgs) -> Result<(), String> {
+/// Run every security check, returning the names of the checks that passed
+pub fn run_all_checks(args: &UpgradeArgs) -> Result<Vec<String>, String> {
+    validate_skipped_checks(args)?;
     let mut context = SecurityCheckContext::new();
 
-    // First, get contract info which will be used by multiple checks
+    // The other checks read the new code from the ledger or from a --wasm file matching it
+    let installed_check = wasm_installed_check::WasmInstalledCheck::new();
+    println!("Running security check: {}", installed_check.name());
+    installed_check.run(args, &mut context)?;
+    let mut passed = vec![installed_check.name().to_string()];
+
+    // Then, get contract info which will be used by multiple checks
     contract_info::fetch_contract_interface(args, &mut context)?;
+    context.current_interface = Some(contract_info::fetch_deployed_interface(args)?);
 
     let checks = get_security_checks();
 
     for check in checks {
+        if args.skip_checks.contains(&check_id(check.name())) {
+            println!(
+                "⚠️  Skipping security check: {} (--skip-check)",
+                check.name()
+            );
+            continue;
+        }
         println!("Running security check: {}", check.name());
         check.run(args, &mut context)?;
+        passed.push(check.name().to_string());
     }
 
-    Ok(())
+    Ok(passed)
 }
@@ -0,0 +1,190 @@
+use super::version_check::VersionCheck;
+use super::{SecurityCheck, SecurityCheckContext};
+use crate::{capture_command, network_flags, wasm, UpgradeArgs};
+use regex::Regex;
+
+pub struct ProtocolCheck;
+
+impl ProtocolCheck {
+    pub fn new() -> Self {
+        ProtocolCheck
+    }
+
+    /// Get the environment metadata (`contractenvmetav0`) of the new WASM
+    fn get_env_metadata(&self, args: &UpgradeArgs) -> Result<String, String> {
+        let command = format!(
+            "stellar contract info env-meta {} --output json",
+            wasm::code_source(args, &args.wasm_hash)
+        );
+        capture_command(&command)
+            .map_err(|e| format!("Failed to get contract environment metadata: {}", e.trim()))
+    }
+
+    /// Get the protocol version currently active on the target network
+    fn get_network_protocol(&self, args: &UpgradeArgs) -> Result<u32, String> {
+        let command = format!("stellar network info {} --output json", network_flags(args));
+        let info = capture_command(&command)
+            .map_err(|e| format!("Failed to get network information: {}", e.trim()))?;
+        self.extract_network_protocol(&info)
+    }
+
+    /// Extract the protocol version the WASM was built for from env metadata JSON
+    pub fn extract_protocol_version(&self, env_meta_json: &str) -> Result<u32, String> {
+        // Current format: [{"sc_env_meta_kind_interface_version":{"protocol":22,"pre_release":0}}]
+        let protocol = Regex::new(r#""protocol"\s*:\s*(\d+)"#).unwrap();
+        if let Some(captures) = protocol.captures(env_meta_json) {
+            return captures[1]
+                .parse()
+                .map_err(|_| format!("Invalid protocol version: {}", &captures[1]));
+        }
+
+        // Legacy format packs the protocol into the high 32 bits of a single number:
+        // [{"sc_env_meta_kind_interface_version":94489280512}]
+        let legacy = Regex::new(r#""sc_env_meta_kind_interface_version"\s*:\s*(\d+)"#).unwrap();
+        if let Some(captures) = legacy.captures(env_meta_json) {
+            let interface_version: u64 = captures[1]
+                .parse()
+                .map_err(|_| format!("Invalid interface version: {}", &captures[1]))?;
+            return Ok((interface_version >> 32) as u32);
+        }
+
+        Err("Interface version not found in environment metadata".to_string())
+    }
+
+    /// Extract the current protocol version from network information JSON
+    pub fn extract_network_protocol(&self, network_info_json: &str) -> Result<u32, String> {
+        let protocol = Regex::new(r#""protocol_?[vV]ersion"\s*:\s*(\d+)"#).unwrap();
+        match protocol.captures(network_info_json) {
+            Some(captures) => captures[1]
+                .parse()
+                .map_err(|_| format!("Invalid protocol version: {}", &captures[1])),
+            None => Err("Protocol version not found in network information".to_string()),
+        }
+    }
+
+    /// Strip the commit suffix from an SDK version (e.g. "22.0.8#f46e9e06..." -> "22.0.8")
+    pub fn normalize_sdk_version<'a>(&self, sdk_version: &'a str) -> &'a str {
+        sdk_version.split('#').next().unwrap_or(sdk_version)
+    }
+
+    /// Warn if the Soroban SDK version goes backwards between the current and new code
+    fn check_sdk_version(&self, args: &UpgradeArgs) {
+        let version_check = VersionCheck::new();
+
+        let sdk_versions = version_check
+            .get_contract_metadata(args, None)
+            .and_then(|meta| version_check.extract_meta_value(&meta, "rssdkver"))
+            .and_then(|current| {
+                version_check
+                    .get_contract_metadata(args, Some(&args.wasm_hash))
+                    .and_then(|meta| version_check.extract_meta_value(&meta, "rssdkver"))
+                    .map(|new| (current, new))
+            });
+
+        let (current, new) = match sdk_versions {
+            Ok(versions) => versions,
+            Err(e) => {
+                println!("⚠️  Could not compare SDK versions: {}", e);
+                return;
+            }
+        };
+
+        let current = self.normalize_sdk_version(&current);
+        let new = self.normalize_sdk_version(&new);
+
+        match version_check.compare_versions(new, current) {
+            Ok(true) => println!(
+                "⚠️  New code was built with an older SDK ({}) than the current code ({})",
+                new, current
+            ),
+            Ok(false) => println!(
+                "✅ SDK version does not go backwards ({} -> {})",
+                current, new
+            ),
+            Err(e) => println!("⚠️  Could not compare SDK versions: {}", e),
+        }
+    }
+}
+
+impl SecurityCheck for ProtocolCheck {
+    fn name(&self) -> &str {
+        "Protocol Compatibility Check"
+    }
+
+    fn run(&self, args: &UpgradeArgs, _context: &mut SecurityCheckContext) -> Result<(), String> {
+        println!("Fetching new WASM environment metadata...");
+        let env_meta = self.get_env_metadata(args)?;
+        let required_protocol = self.extract_protocol_version(&env_meta)?;
+
+        println!("Fetching network protocol version...");
+        let network_protocol = self.get_network_protocol(args)?;
+
+        println!("Required protocol: {}", required_protocol);
+        println!("Network protocol: {}", network_protocol);
+
+        if required_protocol > network_protocol {
+            return Err(format!(
+                "❌ New WASM requires protocol {} but network '{}' is on protocol {}. The upgrade would fail.",
+                required_protocol, args.network, network_protocol
+            ));
+        }
+        println!(
+            "✅ New WASM protocol ({}) is supported by the network ({})",
+            required_protocol, network_protocol
+        );
+
+        self.check_sdk_version(args);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_protocol_version() {
+        let check = ProtocolCheck::new();
+
+        let env_meta =
+            r#"[{"sc_env_meta_kind_interface_version":{"protocol":22,"pre_release":0}}]"#;
+        assert_eq!(check.extract_protocol_version(env_meta).unwrap(), 22);
+
+        // Legacy packed format: 22 << 32
+        let env_meta = r#"[{"sc_env_meta_kind_interface_version":94489280512}]"#;
+        assert_eq!(check.extract_protocol_version(env_meta).unwrap(), 22);
+    }
+
+    #[test]
+    fn test_extract_protocol_version_not_found() {
+        let check = ProtocolCheck::new();
+
+        let result = check.extract_protocol_version("[]");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_extract_network_protocol() {
+        let check = ProtocolCheck::new();
+
+        let info = r#"{"friendbot_url":null,"passphrase":"Public Global Stellar Network ; September 2015","protocol_version":23}"#;
+        assert_eq!(check.extract_network_protocol(info).unwrap(), 23);
+
+        let info = r#"{"passphrase":"Test SDF Network ; September 2015","protocolVersion":22}"#;
+        assert_eq!(check.extract_network_protocol(info).unwrap(), 22);
+
+        assert!(check.extract_network_protocol("{}").is_err());
+    }
+
+    #[test]
+    fn test_normalize_sdk_version() {
+        let check = ProtocolCheck::new();
+
+        assert_eq!(
+            check.normalize_sdk_version("22.0.8#f46e9e0610213bbb72285566f9dd960ff96d03d8"),
+            "22.0.8"
+        );
+        assert_eq!(check.normalize_sdk_version("22.0.8"), "22.0.8");
+    }
+}
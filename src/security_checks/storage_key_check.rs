@@ -0,0 +1,345 @@
+use super::contract_info::{self, ContractEnum};
+use super::{SecurityCheck, SecurityCheckContext};
+use crate::UpgradeArgs;
+
+/// Doc comment marking a `#[contracttype]` enum as a storage key enum
+const STORAGE_KEY_ANNOTATION: &str = "@storage-key";
+
+/// Name of the storage key enum when none is configured
+const DEFAULT_STORAGE_ENUM: &str = "DataKey";
+
+pub struct StorageKeyCheck;
+
+impl StorageKeyCheck {
+    pub fn new() -> Self {
+        StorageKeyCheck
+    }
+
+    /// Whether an enum keys the contract storage, by configured name or annotation
+    pub fn is_storage_enum(&self, args: &UpgradeArgs, contract_enum: &ContractEnum) -> bool {
+        let by_name = if args.storage_enums.is_empty() {
+            contract_enum.name == DEFAULT_STORAGE_ENUM
+        } else {
+            args.storage_enums.contains(&contract_enum.name)
+        };
+        let by_annotation = contract_enum
+            .doc
+            .iter()
+            .any(|line| line.contains(STORAGE_KEY_ANNOTATION));
+
+        by_name || by_annotation
+    }
+
+    /// List the incompatibilities between the current and new version of a storage key enum
+    ///
+    /// When rolling back, keys the restored code doesn't know are expected, see `dropped_keys`.
+    pub fn compare_enums(
+        &self,
+        current: &ContractEnum,
+        new: Option<&ContractEnum>,
+        rollback: bool,
+    ) -> Vec<String> {
+        let new = match new {
+            Some(new) => new,
+            None if rollback => return Vec::new(),
+            None => return vec![format!("`{}` was removed", current.name)],
+        };
+
+        let mut issues = Vec::new();
+        for (index, variant) in current.variants.iter().enumerate() {
+            let Some(new_index) = new.variants.iter().position(|v| v.name == variant.name) else {
+                if !rollback {
+                    issues.push(format!("`{}::{}` was removed", current.name, variant.name));
+                }
+                continue;
+            };
+            let new_variant = &new.variants[new_index];
+
+            if new_index != index {
+                issues.push(format!(
+                    "`{}::{}` was moved from position {} to {}",
+                    current.name, variant.name, index, new_index
+                ));
+            }
+            if new_variant.value != variant.value {
+                issues.push(format!(
+                    "`{}::{}` value changed from {:?} to {:?}",
+                    current.name, variant.name, variant.value, new_variant.value
+                ));
+            }
+            if new_variant.fields != variant.fields {
+                issues.push(format!(
+                    "`{}::{}` fields changed from ({}) to ({})",
+                    current.name,
+                    variant.name,
+                    variant.fields.join(", "),
+                    new_variant.fields.join(", ")
+                ));
+            }
+        }
+
+        issues
+    }
+
+    /// Keys of the current enum that the new version doesn't have anymore
+    pub fn dropped_keys(&self, current: &ContractEnum, new: Option<&ContractEnum>) -> Vec<String> {
+        match new {
+            None => vec![current.name.clone()],
+            Some(new) => current
+                .variants
+                .iter()
+                .filter(|variant| !new.variants.iter().any(|v| v.name == variant.name))
+                .map(|variant| format!("{}::{}", current.name, variant.name))
+                .collect(),
+        }
+    }
+}
+
+impl SecurityCheck for StorageKeyCheck {
+    fn name(&self) -> &str {
+        "Storage Key Check"
+    }
+
+    fn run(&self, args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
+        let (Some(new_interface), Some(current_interface)) =
+            (&context.contract_interface, &context.current_interface)
+        else {
+            return Err("Contract interface information not available".to_string());
+        };
+
+        let new_enums = contract_info::parse_enums(new_interface);
+        let current_enums: Vec<ContractEnum> = contract_info::parse_enums(current_interface)
+            .into_iter()
+            .filter(|contract_enum| self.is_storage_enum(args, contract_enum))
+            .collect();
+
+        if current_enums.is_empty() {
+            println!("✅ No storage key enum found in the current contract");
+            return Ok(());
+        }
+
+        let issues: Vec<String> = current_enums
+            .iter()
+            .flat_map(|current| {
+                let new = new_enums.iter().find(|new| new.name == current.name);
+                self.compare_enums(current, new, args.rollback)
+            })
+            .collect();
+
+        // The restored code predates the keys added since, their entries are left unread
+        if args.rollback {
+            let dropped: Vec<String> = current_enums
+                .iter()
+                .flat_map(|current| {
+                    let new = new_enums.iter().find(|new| new.name == current.name);
+                    self.dropped_keys(current, new)
+                })
+                .collect();
+            if !dropped.is_empty() {
+                println!(
+                    "⚠️  The restored code doesn't know these storage keys, their entries will be left unread: {}",
+                    dropped.join(", ")
+                );
+            }
+        }
+
+        if issues.is_empty() {
+            println!(
+                "✅ Storage key enums are compatible: {}",
+                current_enums
+                    .iter()
+                    .map(|contract_enum| contract_enum.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            Ok(())
+        } else {
+            Err(format!(
+                "❌ Storage key enums changed, existing storage entries would be orphaned or misread:\n  - {}",
+                issues.join("\n  - ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENT_INTERFACE: &str = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+        pub enum DataKey {
+            Admin,
+            Balance(soroban_sdk::Address),
+        }
+        "#;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test".to_string(),
+            wasm_hash: "test".to_string(),
+            source: "test".to_string(),
+            network: "test".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    fn run_check(args: &UpgradeArgs, current: &str, new: &str) -> Result<(), String> {
+        let mut context = SecurityCheckContext::new();
+        context.current_interface = Some(current.to_string());
+        context.contract_interface = Some(new.to_string());
+
+        StorageKeyCheck::new().run(args, &mut context)
+    }
+
+    #[test]
+    fn test_storage_key_check_added_variant() {
+        let new = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum DataKey {
+            Admin,
+            Balance(soroban_sdk::Address),
+            Paused,
+        }
+        "#;
+
+        assert!(run_check(&create_test_args(), CURRENT_INTERFACE, new).is_ok());
+    }
+
+    #[test]
+    fn test_storage_key_check_removed_variant() {
+        let new = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum DataKey {
+            Balance(soroban_sdk::Address),
+        }
+        "#;
+
+        let result = run_check(&create_test_args(), CURRENT_INTERFACE, new);
+        let error = result.unwrap_err();
+        assert!(error.contains("`DataKey::Admin` was removed"));
+        assert!(error.contains("`DataKey::Balance` was moved from position 1 to 0"));
+    }
+
+    #[test]
+    fn test_storage_key_check_rollback_added_variant() {
+        // The current code added `Paused`, the restored code predates it
+        let current = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum DataKey {
+            Admin,
+            Balance(soroban_sdk::Address),
+            Paused,
+        }
+        "#;
+        let args = UpgradeArgs {
+            rollback: true,
+            ..create_test_args()
+        };
+
+        assert!(run_check(&create_test_args(), current, CURRENT_INTERFACE).is_err());
+        assert!(run_check(&args, current, CURRENT_INTERFACE).is_ok());
+
+        let current_enum = &contract_info::parse_enums(current)[0];
+        let restored_enum = &contract_info::parse_enums(CURRENT_INTERFACE)[0];
+        assert_eq!(
+            StorageKeyCheck::new().dropped_keys(current_enum, Some(restored_enum)),
+            vec!["DataKey::Paused"]
+        );
+    }
+
+    #[test]
+    fn test_storage_key_check_rollback_changed_fields() {
+        let current = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum DataKey {
+            Admin,
+            Balance(soroban_sdk::Address, u32),
+        }
+        "#;
+        let args = UpgradeArgs {
+            rollback: true,
+            ..create_test_args()
+        };
+
+        // Entries written by the current code would still be misread by the restored code
+        let result = run_check(&args, current, CURRENT_INTERFACE);
+        assert!(result.unwrap_err().contains("fields changed"));
+    }
+
+    #[test]
+    fn test_storage_key_check_changed_fields() {
+        let new = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum DataKey {
+            Admin,
+            Balance(soroban_sdk::Address, u32),
+        }
+        "#;
+
+        let result = run_check(&create_test_args(), CURRENT_INTERFACE, new);
+        assert!(result.unwrap_err().contains("fields changed"));
+    }
+
+    #[test]
+    fn test_storage_key_check_removed_enum() {
+        let result = run_check(&create_test_args(), CURRENT_INTERFACE, "");
+        assert!(result.unwrap_err().contains("`DataKey` was removed"));
+    }
+
+    #[test]
+    fn test_storage_key_check_configured_name() {
+        let current = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum StorageKey {
+            Admin,
+            Owner,
+        }
+        "#;
+        let new = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum StorageKey {
+            Owner,
+            Admin,
+        }
+        "#;
+
+        // Not a storage key enum by default
+        assert!(run_check(&create_test_args(), current, new).is_ok());
+
+        let mut args = create_test_args();
+        args.storage_enums = vec!["StorageKey".to_string()];
+        assert!(run_check(&args, current, new).is_err());
+    }
+
+    #[test]
+    fn test_storage_key_check_annotation() {
+        let current = r#"
+        /// @storage-key
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum Key {
+            First = 1,
+            Second = 2,
+        }
+        "#;
+        let new = r#"
+        /// @storage-key
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum Key {
+            First = 1,
+            Second = 3,
+        }
+        "#;
+
+        let result = run_check(&create_test_args(), current, new);
+        assert!(result.unwrap_err().contains("value changed"));
+    }
+
+    #[test]
+    fn test_storage_key_check_no_interface() {
+        let mut context = SecurityCheckContext::new();
+        let result = StorageKeyCheck::new().run(&create_test_args(), &mut context);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use super::contract_info::{self, ContractEnum, ContractStruct};
+use super::{SecurityCheck, SecurityCheckContext};
+use crate::ledger::{self, ContractDataEntry};
+use crate::{network_flags, UpgradeArgs};
+use serde_json::Value;
+
+pub struct StorageSnapshotCheck;
+
+impl StorageSnapshotCheck {
+    pub fn new() -> Self {
+        StorageSnapshotCheck
+    }
+
+    /// Whether the live storage must be snapshotted, always the case on mainnet
+    pub fn should_snapshot(&self, args: &UpgradeArgs) -> bool {
+        args.snapshot || args.network == "mainnet"
+    }
+
+    /// Capture the instance storage and the configured persistent entries
+    fn capture(&self, args: &UpgradeArgs) -> Result<Vec<ContractDataEntry>, String> {
+        let instance = ledger::fetch_contract_instance(&args.id, &network_flags(args))?;
+        let mut entries = ledger::parse_instance_storage(&instance)?;
+
+        for key_xdr in &args.snapshot_keys {
+            let entry_json =
+                ledger::fetch_contract_data(&args.id, key_xdr, "persistent", &network_flags(args))
+                    .map_err(|e| {
+                        format!("Failed to fetch storage entry {}: {}", key_xdr, e.trim())
+                    })?;
+            match ledger::parse_contract_data(&entry_json)? {
+                Some(entry) => entries.push(entry),
+                None => println!("⚠️  No persistent storage entry for key {}", key_xdr),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Decode a storage key against the enums of the new code, returning whether it decoded
+    ///
+    /// Keys made of a symbol naming an enum variant followed by values are enum variants.
+    /// Other keys, e.g. tuples starting with a symbol, are left undecoded.
+    pub fn decode_key(&self, key: &Value, enums: &[ContractEnum]) -> Result<bool, String> {
+        let Some((name, fields)) = self.split_variant(key) else {
+            return Ok(false);
+        };
+
+        let variants: Vec<_> = enums
+            .iter()
+            .flat_map(|contract_enum| {
+                contract_enum
+                    .variants
+                    .iter()
+                    .filter(|variant| variant.name == name)
+                    .map(move |variant| (contract_enum, variant))
+            })
+            .collect();
+
+        if variants.is_empty() {
+            return Ok(false);
+        }
+        if variants
+            .iter()
+            .any(|(_, variant)| variant.fields.len() == fields.len())
+        {
+            return Ok(true);
+        }
+
+        let (contract_enum, variant) = variants[0];
+        Err(format!(
+            "key `{}::{}` has {} field(s) in storage but {} in the new code",
+            contract_enum.name,
+            variant.name,
+            fields.len(),
+            variant.fields.len()
+        ))
+    }
+
+    /// Split an enum variant encoded as `[symbol, fields...]`
+    fn split_variant<'a>(&self, key: &'a Value) -> Option<(&'a str, &'a [Value])> {
+        let items = key["vec"].as_array()?;
+        let (first, fields) = items.split_first()?;
+        Some((first["symbol"].as_str()?, fields))
+    }
+
+    /// Describe a storage key for the report
+    fn describe_key(&self, key: &Value) -> String {
+        match self.split_variant(key) {
+            Some((name, [])) => name.to_string(),
+            Some((name, fields)) => format!(
+                "{}({})",
+                name,
+                fields
+                    .iter()
+                    .map(Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            None => key.to_string(),
+        }
+    }
+
+    /// Decode every captured entry, returning the entries that fail to decode and the
+    /// entries whose key isn't an enum variant of the new code
+    pub fn decode_entries(
+        &self,
+        entries: &[ContractDataEntry],
+        interface: &str,
+        current_interface: &str,
+    ) -> (Vec<String>, Vec<String>) {
+        let enums = contract_info::parse_enums(interface);
+        let structs = contract_info::parse_structs(interface);
+        let current_structs = contract_info::parse_structs(current_interface);
+
+        let mut failures = Vec::new();
+        let mut undecoded = Vec::new();
+        for entry in entries {
+            let description = format!(
+                "{} entry {}",
+                entry.durability,
+                self.describe_key(&entry.key)
+            );
+            match self.decode_key(&entry.key, &enums).and_then(|decoded| {
+                decode_value(&entry.val, &structs, &current_structs).map(|_| decoded)
+            }) {
+                Ok(true) => {}
+                Ok(false) => undecoded.push(description),
+                Err(e) => failures.push(format!("{}: {}", description, e)),
+            }
+        }
+
+        (failures, undecoded)
+    }
+}
+
+/// Whether `field_names`, sorted, are the fields of one of `structs`
+fn matches_struct(field_names: &[&str], structs: &[ContractStruct]) -> bool {
+    structs.iter().any(|contract_struct| {
+        let mut expected: Vec<&str> = contract_struct
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        expected.sort_unstable();
+        expected == field_names
+    })
+}
+
+/// Decode a storage value against the structs of the new code
+///
+/// Maps keyed by symbols matching the fields of a struct of the current code are that
+/// struct, and must match the fields of a struct of the new code, nested values included.
+/// Other maps, e.g. `Map<Symbol, u32>`, and other values are left as is.
+pub fn decode_value(
+    val: &Value,
+    structs: &[ContractStruct],
+    current_structs: &[ContractStruct],
+) -> Result<(), String> {
+    if let Some(items) = val["vec"].as_array() {
+        return items
+            .iter()
+            .try_for_each(|item| decode_value(item, structs, current_structs));
+    }
+
+    let Some(map) = val["map"].as_array() else {
+        return Ok(());
+    };
+    let field_names: Option<Vec<&str>> = map
+        .iter()
+        .map(|item| item["key"]["symbol"].as_str())
+        .collect();
+
+    if let Some(mut field_names) = field_names {
+        field_names.sort_unstable();
+        if !matches_struct(&field_names, structs) && matches_struct(&field_names, current_structs) {
+            return Err(format!(
+                "value with fields ({}) matches no struct of the new code",
+                field_names.join(", ")
+            ));
+        }
+    }
+
+    map.iter()
+        .try_for_each(|item| decode_value(&item["val"], structs, current_structs))
+}
+
+impl SecurityCheck for StorageSnapshotCheck {
+    fn name(&self) -> &str {
+        "Storage Snapshot Check"
+    }
+
+    fn run(&self, args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
+        if !self.should_snapshot(args) {
+            println!("⚠️  Skipped: storage is only snapshotted on mainnet or with --snapshot");
+            return Ok(());
+        }
+
+        let interface = context
+            .contract_interface
+            .clone()
+            .ok_or_else(|| "Contract interface information not available".to_string())?;
+
+        let current_interface = context.current_interface.clone().unwrap_or_default();
+
+        let entries = self.capture(args)?;
+        let (failures, undecoded) = self.decode_entries(&entries, &interface, &current_interface);
+
+        if !failures.is_empty() {
+            return Err(format!(
+                "❌ {} of {} storage entries fail to decode with the new code's types:\n  - {}",
+                failures.len(),
+                entries.len(),
+                failures.join("\n  - ")
+            ));
+        }
+
+        if !undecoded.is_empty() {
+            println!(
+                "⚠️  {} storage keys aren't enum variants of the new code, only their values were decoded:\n  - {}",
+                undecoded.len(),
+                undecoded.join("\n  - ")
+            );
+        }
+        println!(
+            "✅ {} storage entries decode with the new code's types",
+            entries.len() - undecoded.len()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NEW_INTERFACE: &str = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub struct Config {
+            pub admin: soroban_sdk::Address,
+            pub fee: u32,
+        }
+
+        #[soroban_sdk::contracttype(export = false)]
+        pub enum DataKey {
+            Config,
+            Balance(soroban_sdk::Address),
+        }
+        "#;
+
+    const CURRENT_INTERFACE: &str = r#"
+        #[soroban_sdk::contracttype(export = false)]
+        pub struct Config {
+            pub admin: soroban_sdk::Address,
+            pub fee: u32,
+            pub paused: bool,
+        }
+
+        #[soroban_sdk::contracttype(export = false)]
+        pub struct Settings {
+            pub paused: bool,
+        }
+        "#;
+
+    const ADMIN: &str = "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54";
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test".to_string(),
+            wasm_hash: "test".to_string(),
+            source: "test".to_string(),
+            network: "test".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    fn entry(key: Value, val: Value) -> ContractDataEntry {
+        ContractDataEntry {
+            durability: "instance".to_string(),
+            key,
+            val,
+            live_until: None,
+        }
+    }
+
+    fn config(fields: &[(&str, Value)]) -> Value {
+        json!({
+            "map": fields
+                .iter()
+                .map(|(name, val)| json!({"key": {"symbol": name}, "val": val}))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    #[test]
+    fn test_decode_entries_compatible() {
+        let check = StorageSnapshotCheck::new();
+        let entries = vec![
+            entry(
+                json!({"vec": [{"symbol": "Config"}]}),
+                config(&[
+                    ("admin", json!({"address": ADMIN})),
+                    ("fee", json!({"u32": 5})),
+                ]),
+            ),
+            entry(
+                json!({"vec": [{"symbol": "Balance"}, {"address": ADMIN}]}),
+                json!({"i128": "100"}),
+            ),
+        ];
+
+        let (failures, undecoded) =
+            check.decode_entries(&entries, NEW_INTERFACE, CURRENT_INTERFACE);
+        assert!(failures.is_empty());
+        assert!(undecoded.is_empty());
+    }
+
+    #[test]
+    fn test_decode_entries_undecoded_keys() {
+        let check = StorageSnapshotCheck::new();
+        let entries = vec![
+            entry(json!({"symbol": "COUNTER"}), json!({"u32": 1})),
+            // Tuple key `(Symbol, Address)`
+            entry(
+                json!({"vec": [{"symbol": "Allowance"}, {"address": ADMIN}]}),
+                json!({"i128": "5"}),
+            ),
+            // Undecoded keys still have their values decoded
+            entry(
+                json!({"vec": [{"symbol": "Settings"}]}),
+                config(&[("paused", json!({"bool": false}))]),
+            ),
+        ];
+
+        let (failures, undecoded) =
+            check.decode_entries(&entries, NEW_INTERFACE, CURRENT_INTERFACE);
+        assert_eq!(undecoded.len(), 2);
+        assert!(undecoded[0].contains("COUNTER"));
+        assert!(undecoded[1].starts_with("instance entry Allowance("));
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("fields (paused) matches no struct"));
+    }
+
+    #[test]
+    fn test_decode_entries_incompatible() {
+        let check = StorageSnapshotCheck::new();
+        let entries = vec![
+            // Struct lost a field
+            entry(
+                json!({"vec": [{"symbol": "Config"}]}),
+                config(&[
+                    ("admin", json!({"address": ADMIN})),
+                    ("fee", json!({"u32": 5})),
+                    ("paused", json!({"bool": false})),
+                ]),
+            ),
+            // Variant fields changed
+            entry(json!({"vec": [{"symbol": "Balance"}]}), json!({"u32": 1})),
+        ];
+
+        let (failures, _) = check.decode_entries(&entries, NEW_INTERFACE, CURRENT_INTERFACE);
+        assert_eq!(failures.len(), 2);
+        assert!(failures[0].contains("fields (admin, fee, paused) matches no struct"));
+        assert!(failures[1].contains("`DataKey::Balance` has 0 field(s) in storage but 1"));
+    }
+
+    #[test]
+    fn test_decode_nested_value() {
+        let structs = contract_info::parse_structs(NEW_INTERFACE);
+        let current_structs = contract_info::parse_structs(CURRENT_INTERFACE);
+
+        let nested = json!({"vec": [config(&[("paused", json!({"bool": true}))])]});
+        assert!(decode_value(&nested, &structs, &current_structs).is_err());
+    }
+
+    #[test]
+    fn test_decode_symbol_map_value() {
+        let structs = contract_info::parse_structs(NEW_INTERFACE);
+        let current_structs = contract_info::parse_structs(CURRENT_INTERFACE);
+
+        // A `Map<Symbol, u32>` whose keys are no struct's fields isn't a struct
+        let limits = config(&[
+            ("daily", json!({"u32": 10})),
+            ("weekly", json!({"u32": 50})),
+        ]);
+        assert!(decode_value(&limits, &structs, &current_structs).is_ok());
+
+        let check = StorageSnapshotCheck::new();
+        let entries = vec![entry(json!({"vec": [{"symbol": "Config"}]}), limits)];
+        let (failures, undecoded) =
+            check.decode_entries(&entries, NEW_INTERFACE, CURRENT_INTERFACE);
+        assert!(failures.is_empty());
+        assert!(undecoded.is_empty());
+    }
+
+    #[test]
+    fn test_should_snapshot() {
+        let check = StorageSnapshotCheck::new();
+        let mut args = create_test_args();
+        assert!(!check.should_snapshot(&args));
+
+        args.network = "mainnet".to_string();
+        assert!(check.should_snapshot(&args));
+
+        args.network = "testnet".to_string();
+        args.snapshot = true;
+        assert!(check.should_snapshot(&args));
+    }
+}
@@ -11,21 +11,32 @@ mod integration_tests {
             id: "test_id".to_string(),
             wasm_hash: "test_hash".to_string(),
             source: "test_source".to_string(),
-            network: "testnet".to_string(),
-            rpc_url: None,
-            rpc_header: None,
-            network_passphrase: None,
-            fee: 100,
-            is_view: false,
-            instructions: None,
-            build_only: false,
-            send: None,
-            cost: false,
-            force: false,
-            contract_args: vec![],
+            ..UpgradeArgs::for_test()
         }
     }
 
+    #[test]
+    fn test_check_ids() {
+        assert_eq!(
+            crate::security_checks::check_id("Unprotected Upgrade Check"),
+            "unprotected-upgrade"
+        );
+        assert_eq!(crate::security_checks::check_id("TTL Check"), "ttl");
+    }
+
+    #[test]
+    fn test_unknown_skipped_check() {
+        let args = UpgradeArgs {
+            skip_checks: vec!["unprotected".to_string()],
+            ..create_test_args()
+        };
+
+        let result = crate::security_checks::run_all_checks(&args);
+        assert!(result
+            .unwrap_err()
+            .contains("Unknown security check `unprotected`"));
+    }
+
     #[test]
     fn test_all_checks_pass() {
         let mut context = SecurityCheckContext::new();
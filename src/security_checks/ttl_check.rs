@@ -0,0 +1,111 @@
+use super::{SecurityCheck, SecurityCheckContext};
+use crate::ttl::{self, EntryTtl};
+use crate::UpgradeArgs;
+
+pub struct TtlCheck;
+
+impl TtlCheck {
+    pub fn new() -> Self {
+        TtlCheck
+    }
+
+    /// Report the TTL of each entry, failing on archived entries
+    pub fn evaluate(&self, args: &UpgradeArgs, ttls: &[EntryTtl]) -> Result<(), String> {
+        let archived: Vec<&str> = ttls
+            .iter()
+            .filter(|ttl| ttl.is_archived())
+            .map(|ttl| ttl.target.describe())
+            .collect();
+        if !archived.is_empty() {
+            return Err(format!(
+                "❌ Archived, restore with `stellar contract restore` before upgrading: {}",
+                archived.join(", ")
+            ));
+        }
+
+        for ttl in ttls {
+            if ttl.remaining() >= u64::from(args.min_ttl) {
+                println!(
+                    "✅ The {} lives for {} more ledgers",
+                    ttl.target.describe(),
+                    ttl.remaining()
+                );
+            } else if args.extend_ttl {
+                println!(
+                    "⚠️  The {} expires in {} ledgers, it will be extended before the upgrade",
+                    ttl.target.describe(),
+                    ttl.remaining()
+                );
+            } else {
+                println!(
+                    "⚠️  The {} expires in {} ledgers (below --min-ttl {}), pass --extend-ttl to extend it",
+                    ttl.target.describe(),
+                    ttl.remaining(),
+                    args.min_ttl
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SecurityCheck for TtlCheck {
+    fn name(&self) -> &str {
+        "TTL Check"
+    }
+
+    fn run(&self, args: &UpgradeArgs, _context: &mut SecurityCheckContext) -> Result<(), String> {
+        let ttls = ttl::fetch_ttls(args)?;
+        self.evaluate(args, &ttls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ttl::TtlTarget;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test".to_string(),
+            wasm_hash: "test".to_string(),
+            source: "test".to_string(),
+            network: "test".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    fn entry_ttl(target: TtlTarget, live_until: u64) -> EntryTtl {
+        EntryTtl {
+            target,
+            live_until,
+            latest_ledger: 1000,
+        }
+    }
+
+    #[test]
+    fn test_ttl_check_live() {
+        let mut args = create_test_args();
+        args.min_ttl = 500;
+
+        let ttls = vec![
+            entry_ttl(TtlTarget::Instance, 2000),
+            entry_ttl(TtlTarget::Code, 1200),
+        ];
+        // Entries below the threshold only warn
+        assert!(TtlCheck::new().evaluate(&args, &ttls).is_ok());
+    }
+
+    #[test]
+    fn test_ttl_check_archived() {
+        let args = create_test_args();
+
+        let ttls = vec![
+            entry_ttl(TtlTarget::Instance, 2000),
+            entry_ttl(TtlTarget::Code, 900),
+        ];
+        let result = TtlCheck::new().evaluate(&args, &ttls);
+        assert!(result.unwrap_err().contains("new WASM code entry"));
+    }
+}
@@ -0,0 +1,341 @@
+use super::{SecurityCheck, SecurityCheckContext};
+use crate::authorization;
+use crate::simulation::{self, SimulationReport};
+use crate::{capture_command, network_flags, UpgradeArgs};
+use regex::Regex;
+
+/// Name of the throwaway identity used to call `upgrade` without authorization
+const PROBE_IDENTITY: &str = "stellar-upgrader-probe";
+
+pub struct UnprotectedUpgradeCheck;
+
+impl UnprotectedUpgradeCheck {
+    pub fn new() -> Self {
+        UnprotectedUpgradeCheck
+    }
+
+    /// Run a sandbox setup step, describing which step failed on error
+    fn run_step(&self, command: &str, step: &str) -> Result<String, String> {
+        capture_command(command).map_err(|e| format!("Failed to {}: {}", step, e.trim()))
+    }
+
+    /// Deploy the candidate WASM on the sandbox network and return the contract ID
+    fn deploy_candidate(&self, args: &UpgradeArgs, sandbox: &str) -> Result<String, String> {
+        // The --wasm file may not be uploaded to the target network yet
+        let wasm_path = match &args.wasm {
+            Some(path) => path.clone(),
+            None => {
+                let wasm_path = std::env::temp_dir()
+                    .join(format!("stellar-upgrader-{}.wasm", args.wasm_hash))
+                    .display()
+                    .to_string();
+                self.run_step(
+                    &format!(
+                        "stellar contract fetch --wasm-hash {} {} --out-file {}",
+                        args.wasm_hash,
+                        network_flags(args),
+                        wasm_path
+                    ),
+                    "fetch the new WASM",
+                )?;
+                wasm_path
+            }
+        };
+
+        self.run_step(
+            &format!(
+                "stellar keys generate {} --network {} --fund --overwrite",
+                PROBE_IDENTITY, sandbox
+            ),
+            "create the throwaway account",
+        )?;
+
+        self.run_step(
+            &format!(
+                "stellar contract upload --wasm {} --source {} --network {}",
+                wasm_path, PROBE_IDENTITY, sandbox
+            ),
+            "upload the new WASM to the sandbox",
+        )?;
+
+        let output = self.run_step(
+            &self.deploy_command(args, sandbox),
+            "deploy the new WASM to the sandbox (pass constructor arguments with --probe-constructor-arg)",
+        )?;
+
+        self.extract_contract_id(&output)
+    }
+
+    /// Generate the command deploying the new WASM to the sandbox with its constructor arguments
+    pub fn deploy_command(&self, args: &UpgradeArgs, sandbox: &str) -> String {
+        let mut command = format!(
+            "stellar contract deploy --wasm-hash {} --source {} --network {}",
+            args.wasm_hash, PROBE_IDENTITY, sandbox
+        );
+        if !args.probe_constructor_args.is_empty() {
+            command.push_str(" --");
+            for (name, value) in &args.probe_constructor_args {
+                command.push_str(&format!(" --{} {}", name, value));
+            }
+        }
+        command
+    }
+
+    /// Extract the deployed contract ID from `stellar contract deploy` output
+    pub fn extract_contract_id(&self, output: &str) -> Result<String, String> {
+        let contract_id = Regex::new(r"\bC[A-Z2-7]{55}\b").unwrap();
+        contract_id
+            .find_iter(output)
+            .last()
+            .map(|m| m.as_str().to_string())
+            .ok_or_else(|| "Contract ID not found in deploy output".to_string())
+    }
+
+    /// Whether a simulation error is the host rejecting the call for lack of authorization
+    ///
+    /// Other errors (RPC, network, CLI, a missing function, or a contract error such as an
+    /// uninitialized contract) say nothing about its protection.
+    pub fn is_auth_rejection(&self, error: &str) -> bool {
+        error.contains("Error(Auth, ")
+    }
+
+    /// Arguments calling the upgrade entrypoint of the sandbox deployment from the probe account
+    ///
+    /// The entrypoint is the one detected by the upgrade function check. The call goes to the
+    /// sandbox deployment itself, never through a router or an atomic migration.
+    pub fn probe_args(
+        &self,
+        args: &UpgradeArgs,
+        context: &SecurityCheckContext,
+        sandbox_id: &str,
+        sandbox: &str,
+    ) -> UpgradeArgs {
+        let mut probe_args = UpgradeArgs {
+            id: sandbox_id.to_string(),
+            source: PROBE_IDENTITY.to_string(),
+            network: sandbox.to_string(),
+            rpc_url: None,
+            rpc_header: None,
+            network_passphrase: None,
+            router: None,
+            migrate: None,
+            migrate_args: vec![],
+            ..args.clone()
+        };
+        if let Some(entrypoint) = &context.upgrade_entrypoint {
+            probe_args.upgrade_fn = Some(entrypoint.function.clone());
+            probe_args.hash_arg = Some(entrypoint.hash_param.clone());
+        }
+        probe_args
+    }
+
+    /// Decide whether the probe simulation shows `upgrade` is callable without authorization
+    pub fn verdict(
+        &self,
+        simulation: &Result<SimulationReport, String>,
+        probe_address: &str,
+    ) -> Result<(), String> {
+        match simulation {
+            Err(e) if self.is_auth_rejection(e) => {
+                println!("✅ New code rejects `upgrade` calls from an unauthorized account");
+                Ok(())
+            }
+            Err(e) if e.contains("Error(Contract, ") => Err(format!(
+                "❌ The sandbox deployment failed `upgrade` with a contract error before checking authorization, e.g. because it isn't initialized: {}\n\
+                Initialize it with --probe-constructor-arg, or skip this check with --skip-check unprotected-upgrade.",
+                e.trim()
+            )),
+            Err(e) => Err(format!(
+                "❌ Couldn't probe whether the new code protects `upgrade`: {}",
+                e.trim()
+            )),
+            Ok(report) if !authorization::required_signers(&report.auth, probe_address).is_empty() => {
+                println!("✅ New code requires authorization to call `upgrade`");
+                Ok(())
+            }
+            Ok(_) => Err("❌ New code lets any account call `upgrade`. Anyone would be able to replace the contract code.".to_string()),
+        }
+    }
+}
+
+impl SecurityCheck for UnprotectedUpgradeCheck {
+    fn name(&self) -> &str {
+        "Unprotected Upgrade Check"
+    }
+
+    fn run(&self, args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
+        let sandbox =
+            match &args.sandbox_network {
+                Some(sandbox) => sandbox,
+                None => return Err(
+                    "❌ No --sandbox-network to probe whether the new code protects `upgrade` on. \
+                    Pass a sandbox network (e.g. local), or skip this check with --skip-check unprotected-upgrade."
+                        .to_string(),
+                ),
+            };
+
+        println!("Deploying new WASM to sandbox network '{}'...", sandbox);
+        let sandbox_id = self.deploy_candidate(args, sandbox)?;
+        let probe_address = authorization::resolve_source_address(PROBE_IDENTITY)?;
+
+        let probe_args = self.probe_args(args, context, &sandbox_id, sandbox);
+
+        println!(
+            "Calling `{}` from an unauthorized account...",
+            probe_args.upgrade_fn.as_deref().unwrap_or("upgrade")
+        );
+        let simulation = simulation::simulate_upgrade(&probe_args);
+
+        self.verdict(&simulation, &probe_address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::AuthRequirement;
+
+    const PROBE: &str = "GBZXN7PIRZGNMHGA7MUUUF4GWPY5AYPV6LY4UV2GL6VJGIQRXFDNMADI";
+    const ADMIN: &str = "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54";
+
+    fn report(signers: &[Option<&str>]) -> SimulationReport {
+        SimulationReport {
+            fee: 0,
+            resource_fee: 0,
+            instructions: 0,
+            read_bytes: 0,
+            write_bytes: 0,
+            read_only: vec![],
+            read_write: vec![],
+            auth: signers
+                .iter()
+                .map(|signer| AuthRequirement {
+                    signer: signer.map(str::to_string),
+                    contract: "CABC".to_string(),
+                    function: "upgrade".to_string(),
+                })
+                .collect(),
+            xdr: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_extract_contract_id() {
+        let check = UnprotectedUpgradeCheck::new();
+
+        let output =
+            "ℹ️  Deploying contract...\nCABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX\n";
+        assert_eq!(
+            check.extract_contract_id(output).unwrap(),
+            "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX"
+        );
+
+        assert!(check.extract_contract_id("error").is_err());
+    }
+
+    #[test]
+    fn test_verdict_protected() {
+        let check = UnprotectedUpgradeCheck::new();
+
+        // Upgrade requires another account's authorization
+        assert!(check.verdict(&Ok(report(&[Some(ADMIN)])), PROBE).is_ok());
+
+        // Upgrade rejects the call for lack of authorization
+        assert!(check
+            .verdict(
+                &Err("HostError: Error(Auth, InvalidAction)".to_string()),
+                PROBE
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_verdict_probe_failed() {
+        let check = UnprotectedUpgradeCheck::new();
+
+        // Failures to run the probe don't show the upgrade is protected
+        let errors = [
+            "error: transport error: Connection refused (os error 111)",
+            "sh: 1: stellar: not found",
+            "error: unexpected argument '--new_wasm_hash' found",
+            "HostError: Error(WasmVm, MissingValue)",
+        ];
+        for error in errors {
+            let verdict = check.verdict(&Err(error.to_string()), PROBE);
+            assert!(verdict.unwrap_err().contains("Couldn't probe"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_verdict_contract_error() {
+        let check = UnprotectedUpgradeCheck::new();
+
+        // E.g. an uninitialized contract failing before it checks authorization
+        let verdict = check.verdict(&Err("HostError: Error(Contract, #1)".to_string()), PROBE);
+        assert!(verdict.unwrap_err().contains("--probe-constructor-arg"));
+    }
+
+    #[test]
+    fn test_deploy_command() {
+        let check = UnprotectedUpgradeCheck::new();
+        let mut args = UpgradeArgs::for_test();
+        assert!(check
+            .deploy_command(&args, "local")
+            .ends_with("--network local"));
+
+        args.probe_constructor_args = vec![("admin".to_string(), "GADMIN".to_string())];
+        assert!(check
+            .deploy_command(&args, "local")
+            .ends_with("--network local -- --admin GADMIN"));
+    }
+
+    #[test]
+    fn test_verdict_unprotected() {
+        let check = UnprotectedUpgradeCheck::new();
+
+        // No authorization at all
+        assert!(check.verdict(&Ok(report(&[])), PROBE).is_err());
+
+        // Only the caller's own authorization
+        assert!(check.verdict(&Ok(report(&[Some(PROBE)])), PROBE).is_err());
+        assert!(check.verdict(&Ok(report(&[None])), PROBE).is_err());
+    }
+
+    #[test]
+    fn test_probe_args_skip_router() {
+        let check = UnprotectedUpgradeCheck::new();
+        let args = UpgradeArgs {
+            router: Some("CROUTER".to_string()),
+            migrate: Some("migrate".to_string()),
+            migrate_args: vec![("version".to_string(), "2".to_string())],
+            ..UpgradeArgs::for_test()
+        };
+
+        let probe_args = check.probe_args(&args, &SecurityCheckContext::new(), "CSANDBOX", "local");
+        assert_eq!(probe_args.router, None);
+        assert_eq!(probe_args.migrate, None);
+        assert!(probe_args.migrate_args.is_empty());
+
+        let command = crate::generate_upgrade_command(&probe_args);
+        assert!(command.contains("--id CSANDBOX --source stellar-upgrader-probe --network local"));
+    }
+
+    #[test]
+    fn test_fails_without_sandbox() {
+        let check = UnprotectedUpgradeCheck::new();
+        let mut context = SecurityCheckContext::new();
+
+        let result = check.run(
+            &UpgradeArgs {
+                id: "test".to_string(),
+                wasm_hash: "test".to_string(),
+                source: "test".to_string(),
+                network: "test".to_string(),
+                ..UpgradeArgs::for_test()
+            },
+            &mut context,
+        );
+
+        assert!(result.unwrap_err().contains("--sandbox-network"));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{SecurityCheck, SecurityCheckContext};
+use super::{contract_info, SecurityCheck, SecurityCheckContext};
 use crate::UpgradeArgs;
 
 pub struct UpgradeFunctionCheck;
@@ -14,17 +14,25 @@ impl SecurityCheck for UpgradeFunctionCheck {
         "Upgrade Function Check"
     }
 
-    fn run(&self, _args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
+    fn run(&self, args: &UpgradeArgs, context: &mut SecurityCheckContext) -> Result<(), String> {
         if let Some(interface) = &context.contract_interface {
-            // Check if the interface contains an upgrade function with the expected signature
-            // The expected signature should include both the function name and the wasm_hash parameter
-            if interface.contains("fn upgrade(")
-                && interface.contains("new_wasm_hash: soroban_sdk::BytesN<32>")
-            {
-                println!("✅ Contract exposes an upgrade function with proper signature");
-                Ok(())
-            } else {
-                Err("❌ Contract does not expose a proper upgrade function. Further upgradeability won't be possible.".to_string())
+            // Look for a function taking the new WASM hash as a `BytesN<32>` parameter,
+            // either the one configured or the one detected from the signatures
+            let functions = contract_info::parse_functions(interface);
+            match contract_info::find_upgrade_entrypoint(
+                &functions,
+                args.upgrade_fn.as_deref(),
+                args.hash_arg.as_deref(),
+            ) {
+                Ok(entrypoint) => {
+                    println!(
+                        "✅ Contract exposes an upgrade function with proper signature: {}",
+                        entrypoint.signature()
+                    );
+                    context.upgrade_entrypoint = Some(entrypoint);
+                    Ok(())
+                }
+                Err(e) => Err(format!("❌ Contract does not expose a proper upgrade function. Further upgradeability won't be possible. {}", e)),
             }
         } else {
             Err("Contract interface information not available".to_string())
@@ -59,17 +67,7 @@ mod tests {
                 wasm_hash: "test".to_string(),
                 source: "test".to_string(),
                 network: "test".to_string(),
-                rpc_url: None,
-                rpc_header: None,
-                network_passphrase: None,
-                fee: 100,
-                is_view: false,
-                instructions: None,
-                build_only: false,
-                send: None,
-                cost: false,
-                force: false,
-                contract_args: vec![],
+                ..UpgradeArgs::for_test()
             },
             &mut context,
         );
@@ -77,6 +75,97 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_upgrade_function_check_detects_custom_entrypoint() {
+        let mut context = SecurityCheckContext::new();
+        context.contract_interface = Some(
+            r#"
+        pub trait Contract {
+            fn version(env: soroban_sdk::Env) -> u32;
+            fn upgrade(
+                env: soroban_sdk::Env,
+                new_wasm_hash: soroban_sdk::BytesN<32>,
+                operator: soroban_sdk::Address,
+            );
+        }
+        "#
+            .to_string(),
+        );
+
+        let check = UpgradeFunctionCheck::new();
+        let result = check.run(
+            &UpgradeArgs {
+                id: "test".to_string(),
+                wasm_hash: "test".to_string(),
+                source: "test".to_string(),
+                network: "test".to_string(),
+                ..UpgradeArgs::for_test()
+            },
+            &mut context,
+        );
+
+        assert!(result.is_ok());
+        let entrypoint = context.upgrade_entrypoint.unwrap();
+        assert_eq!(entrypoint.function, "upgrade");
+        assert_eq!(entrypoint.extra_params.len(), 1);
+        assert_eq!(entrypoint.extra_params[0].name, "operator");
+    }
+
+    #[test]
+    fn test_upgrade_function_check_configured_name_missing() {
+        let mut context = SecurityCheckContext::new();
+        context.contract_interface = Some(
+            r#"
+        pub trait Contract {
+            fn upgrade(env: soroban_sdk::Env, new_wasm_hash: soroban_sdk::BytesN<32>);
+        }
+        "#
+            .to_string(),
+        );
+
+        let check = UpgradeFunctionCheck::new();
+        let result = check.run(
+            &UpgradeArgs {
+                id: "test".to_string(),
+                wasm_hash: "test".to_string(),
+                source: "test".to_string(),
+                network: "test".to_string(),
+                upgrade_fn: Some("set_code".to_string()),
+                ..UpgradeArgs::for_test()
+            },
+            &mut context,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_upgrade_function_check_other_name_requires_upgrade_fn() {
+        let interface = r#"
+        pub trait Contract {
+            fn set_code(env: soroban_sdk::Env, hash: soroban_sdk::BytesN<32>);
+        }
+        "#;
+        let check = UpgradeFunctionCheck::new();
+
+        let mut context = SecurityCheckContext::new();
+        context.contract_interface = Some(interface.to_string());
+        let err = check
+            .run(&UpgradeArgs::for_test(), &mut context)
+            .unwrap_err();
+        assert!(err.contains("--upgrade-fn set_code"));
+        assert!(context.upgrade_entrypoint.is_none());
+
+        let mut context = SecurityCheckContext::new();
+        context.contract_interface = Some(interface.to_string());
+        let args = UpgradeArgs {
+            upgrade_fn: Some("set_code".to_string()),
+            ..UpgradeArgs::for_test()
+        };
+        assert!(check.run(&args, &mut context).is_ok());
+        assert_eq!(context.upgrade_entrypoint.unwrap().function, "set_code");
+    }
+
     #[test]
     fn test_upgrade_function_check_fail_no_function() {
         let mut context = SecurityCheckContext::new();
@@ -99,17 +188,7 @@ mod tests {
                 wasm_hash: "test".to_string(),
                 source: "test".to_string(),
                 network: "test".to_string(),
-                rpc_url: None,
-                rpc_header: None,
-                network_passphrase: None,
-                fee: 100,
-                is_view: false,
-                instructions: None,
-                build_only: false,
-                send: None,
-                cost: false,
-                force: false,
-                contract_args: vec![],
+                ..UpgradeArgs::for_test()
             },
             &mut context,
         );
@@ -140,17 +219,7 @@ mod tests {
                 wasm_hash: "test".to_string(),
                 source: "test".to_string(),
                 network: "test".to_string(),
-                rpc_url: None,
-                rpc_header: None,
-                network_passphrase: None,
-                fee: 100,
-                is_view: false,
-                instructions: None,
-                build_only: false,
-                send: None,
-                cost: false,
-                force: false,
-                contract_args: vec![],
+                ..UpgradeArgs::for_test()
             },
             &mut context,
         );
@@ -1,5 +1,5 @@
 use super::{SecurityCheck, SecurityCheckContext};
-use crate::UpgradeArgs;
+use crate::{network_flags, wasm, UpgradeArgs};
 use std::cmp::Ordering;
 use std::process::Command;
 
@@ -11,20 +11,21 @@ impl VersionCheck {
     }
 
     /// Get contract metadata for a given contract ID or WASM hash
-    fn get_contract_metadata(
+    pub(crate) fn get_contract_metadata(
         &self,
         args: &UpgradeArgs,
         wasm_hash: Option<&str>,
     ) -> Result<String, String> {
         let command = if let Some(hash) = wasm_hash {
             format!(
-                "stellar contract info meta --wasm-hash {} --network {} --output json",
-                hash, args.network
+                "stellar contract info meta {} --output json",
+                wasm::code_source(args, hash)
             )
         } else {
             format!(
-                "stellar contract info meta --id {} --network {} --output json",
-                args.id, args.network
+                "stellar contract info meta --id {} {} --output json",
+                args.id,
+                network_flags(args)
             )
         };
 
@@ -54,21 +55,27 @@ impl VersionCheck {
 
     /// Extract binver from metadata JSON
     pub fn extract_binver(&self, metadata_json: &str) -> Result<String, String> {
-        // Parse the JSON to find binver
+        self.extract_meta_value(metadata_json, "binver")
+    }
+
+    /// Extract the value of a metadata key from metadata JSON
+    pub fn extract_meta_value(&self, metadata_json: &str, key: &str) -> Result<String, String> {
+        // Parse the JSON to find the key
         // The format is: [{"sc_meta_v0":{"key":"binver","val":"2.0.0"}}, ...]
 
-        // Simple JSON parsing - look for "binver" key
-        if let Some(start) = metadata_json.find(r#""key":"binver""#) {
+        // Simple JSON parsing - look for the requested key
+        let key_pattern = format!(r#""key":"{}""#, key);
+        if let Some(start) = metadata_json.find(&key_pattern) {
             if let Some(val_start) = metadata_json[start..].find(r#""val":""#) {
                 let val_start_pos = start + val_start + 7; // 7 = length of `"val":"`
                 if let Some(val_end) = metadata_json[val_start_pos..].find('"') {
-                    let version = &metadata_json[val_start_pos..val_start_pos + val_end];
-                    return Ok(version.to_string());
+                    let value = &metadata_json[val_start_pos..val_start_pos + val_end];
+                    return Ok(value.to_string());
                 }
             }
         }
 
-        Err("binver not found in metadata".to_string())
+        Err(format!("{} not found in metadata", key))
     }
 
     /// Compare two semantic versions (e.g., "1.0.0" vs "2.0.0")
@@ -100,6 +107,32 @@ impl VersionCheck {
         // Versions are equal
         Ok(false)
     }
+
+    /// Check the version change, which must be an increase unless rolling back
+    pub fn check_version_change(
+        &self,
+        current: &str,
+        new: &str,
+        rollback: bool,
+    ) -> Result<String, String> {
+        if rollback {
+            if self.compare_versions(current, new)? {
+                Err(format!("❌ Rollback version ({}) is greater than current version ({}). Use the upgrade command instead.", new, current))
+            } else {
+                Ok(format!(
+                    "⚠️  Rolling back from version {} to version {}",
+                    current, new
+                ))
+            }
+        } else if self.compare_versions(current, new)? {
+            Ok(format!(
+                "✅ New version ({}) is greater than current version ({})",
+                new, current
+            ))
+        } else {
+            Err(format!("❌ New version ({}) is not greater than current version ({}). Version downgrades are not recommended.", new, current))
+        }
+    }
 }
 
 impl SecurityCheck for VersionCheck {
@@ -119,15 +152,11 @@ impl SecurityCheck for VersionCheck {
         println!("Current version: {}", current_version);
         println!("New version: {}", new_version);
 
-        if self.compare_versions(&current_version, &new_version)? {
-            println!(
-                "✅ New version ({}) is greater than current version ({})",
-                new_version, current_version
-            );
-            Ok(())
-        } else {
-            Err(format!("❌ New version ({}) is not greater than current version ({}). Version downgrades are not recommended.", new_version, current_version))
-        }
+        println!(
+            "{}",
+            self.check_version_change(&current_version, &new_version, args.rollback)?
+        );
+        Ok(())
     }
 }
 
@@ -153,6 +182,21 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_extract_meta_value() {
+        let check = VersionCheck::new();
+
+        let metadata = r#"[{"sc_meta_v0":{"key":"binver","val":"2.0.0"}},{"sc_meta_v0":{"key":"rssdkver","val":"22.0.8#f46e9e0610213bbb72285566f9dd960ff96d03d8"}}]"#;
+        let sdk_version = check.extract_meta_value(metadata, "rssdkver").unwrap();
+        assert_eq!(
+            sdk_version,
+            "22.0.8#f46e9e0610213bbb72285566f9dd960ff96d03d8"
+        );
+
+        let result = check.extract_meta_value(metadata, "rsver");
+        assert_eq!(result.unwrap_err(), "rsver not found in metadata");
+    }
+
     #[test]
     fn test_compare_versions() {
         let check = VersionCheck::new();
@@ -188,4 +232,21 @@ mod tests {
         let result = check.compare_versions("1.0.0", "invalid");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_check_version_change() {
+        let check = VersionCheck::new();
+
+        assert!(check.check_version_change("1.0.0", "1.1.0", false).is_ok());
+        assert!(check.check_version_change("1.1.0", "1.0.0", false).is_err());
+        assert!(check.check_version_change("1.0.0", "1.0.0", false).is_err());
+
+        // Rollbacks may decrease the version, but not increase it
+        assert!(check
+            .check_version_change("1.1.0", "1.0.0", true)
+            .unwrap()
+            .contains("Rolling back from version 1.1.0 to version 1.0.0"));
+        assert!(check.check_version_change("1.0.0", "1.0.0", true).is_ok());
+        assert!(check.check_version_change("1.0.0", "1.1.0", true).is_err());
+    }
 }
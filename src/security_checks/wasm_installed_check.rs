@@ -0,0 +1,20 @@
+use super::{SecurityCheck, SecurityCheckContext};
+use crate::{wasm, UpgradeArgs};
+
+pub struct WasmInstalledCheck;
+
+impl WasmInstalledCheck {
+    pub fn new() -> Self {
+        WasmInstalledCheck
+    }
+}
+
+impl SecurityCheck for WasmInstalledCheck {
+    fn name(&self) -> &str {
+        "WASM Installed Check"
+    }
+
+    fn run(&self, args: &UpgradeArgs, _context: &mut SecurityCheckContext) -> Result<(), String> {
+        wasm::check_installed(args).map(|_| ())
+    }
+}
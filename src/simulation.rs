@@ -0,0 +1,300 @@
+use crate::security_checks::contract_info::{self, ContractError};
+use crate::transaction::{self, AuthRequirement};
+use crate::{capture_command, generate_upgrade_command, is_submitted, network_flags, UpgradeArgs};
+use regex::Regex;
+use serde_json::Value;
+
+/// Outcome of simulating the upgrade transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationReport {
+    /// Total fee of the assembled transaction, in stroops
+    pub fee: u64,
+    /// Part of the fee paid for Soroban resources, in stroops
+    pub resource_fee: u64,
+    pub instructions: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub read_only: Vec<String>,
+    pub read_write: Vec<String>,
+    pub auth: Vec<AuthRequirement>,
+    /// Base64 XDR of the assembled transaction
+    pub xdr: String,
+}
+
+impl SimulationReport {
+    /// Build a report from a decoded, simulated transaction envelope
+    pub fn from_envelope(envelope: &Value, xdr: &str) -> Result<Self, String> {
+        let tx = transaction::transaction(envelope)?;
+        let invoke = transaction::invoke_host_function(tx)?;
+
+        let soroban_data = tx["ext"]
+            .get("v1")
+            .ok_or_else(|| "Simulated transaction has no Soroban resources".to_string())?;
+        let resources = &soroban_data["resources"];
+        let footprint = &resources["footprint"];
+
+        let describe_keys = |keys: &Value| -> Vec<String> {
+            keys.as_array()
+                .map(|keys| keys.iter().map(transaction::describe_ledger_key).collect())
+                .unwrap_or_default()
+        };
+
+        Ok(SimulationReport {
+            fee: transaction::as_u64(&tx["fee"]).unwrap_or(0),
+            resource_fee: transaction::as_u64(&soroban_data["resource_fee"]).unwrap_or(0),
+            instructions: transaction::as_u64(&resources["instructions"]).unwrap_or(0),
+            // Renamed from `read_bytes` to `disk_read_bytes` in protocol 23
+            read_bytes: transaction::as_u64(&resources["disk_read_bytes"])
+                .or_else(|| transaction::as_u64(&resources["read_bytes"]))
+                .unwrap_or(0),
+            write_bytes: transaction::as_u64(&resources["write_bytes"]).unwrap_or(0),
+            read_only: describe_keys(&footprint["read_only"]),
+            read_write: describe_keys(&footprint["read_write"]),
+            auth: transaction::auth_requirements(invoke),
+            xdr: xdr.trim().to_string(),
+        })
+    }
+
+    /// Print the report
+    pub fn print(&self) {
+        println!("Simulation results:");
+        println!(
+            "  Fee estimate: {} stroops ({} resource fee)",
+            self.fee, self.resource_fee
+        );
+        println!(
+            "  Resources: {} instructions, {} bytes read, {} bytes written",
+            self.instructions, self.read_bytes, self.write_bytes
+        );
+
+        println!("  Footprint (read-only):");
+        for key in &self.read_only {
+            println!("    - {}", key);
+        }
+        println!("  Footprint (read-write):");
+        for key in &self.read_write {
+            println!("    - {}", key);
+        }
+
+        println!("  Required authorizations:");
+        if self.auth.is_empty() {
+            println!("    (none)");
+        }
+        for auth in &self.auth {
+            println!(
+                "    - {} for {}::{}",
+                auth.signer
+                    .as_deref()
+                    .unwrap_or("transaction source account"),
+                auth.contract,
+                auth.function
+            );
+        }
+    }
+}
+
+/// Whether the upgrade transaction will be sent and must be simulated first
+pub fn should_simulate(args: &UpgradeArgs) -> bool {
+    !args.skip_simulation && is_submitted(args)
+}
+
+/// Generate the command building the unsigned upgrade transaction
+pub fn generate_build_command(args: &UpgradeArgs) -> String {
+    let mut build_args = args.clone();
+    build_args.build_only = true;
+    build_args.is_view = false;
+    build_args.send = None;
+    build_args.cost = false;
+
+    generate_upgrade_command(&build_args)
+}
+
+/// Generate the command simulating an unsigned transaction
+pub fn generate_simulate_command(args: &UpgradeArgs, xdr: &str) -> String {
+    format!(
+        "stellar tx simulate --source {} {} {}",
+        args.source,
+        network_flags(args),
+        xdr.trim()
+    )
+}
+
+/// Replace contract error codes (e.g. `Error(Contract, #1)`) with their names from the spec
+pub fn describe_contract_errors(message: &str, errors: &[ContractError]) -> String {
+    let code = Regex::new(r"Error\(Contract, #(\d+)\)").unwrap();
+
+    code.replace_all(message, |captures: &regex::Captures| {
+        let name = captures[1].parse::<u32>().ok().and_then(|value| {
+            errors
+                .iter()
+                .find(|error| error.code == value)
+                .map(|error| format!("{}::{}", error.enum_name, error.variant))
+        });
+
+        match name {
+            Some(name) => format!("{} ({})", &captures[0], name),
+            None => captures[0].to_string(),
+        }
+    })
+    .into_owned()
+}
+
+/// Decode contract errors in a failed simulation using the deployed contract's spec
+fn decode_simulation_error(args: &UpgradeArgs, stderr: &str) -> String {
+    match contract_info::fetch_deployed_interface(args) {
+        Ok(interface) => {
+            describe_contract_errors(stderr, &contract_info::parse_contract_errors(&interface))
+        }
+        Err(_) => stderr.to_string(),
+    }
+}
+
+/// Simulate the upgrade transaction without submitting it
+pub fn simulate_upgrade(args: &UpgradeArgs) -> Result<SimulationReport, String> {
+    println!("Simulating upgrade transaction...");
+
+    let fail = |stderr: String| {
+        format!(
+            "❌ Simulation of the upgrade transaction failed, refusing to submit:\n{}",
+            decode_simulation_error(args, stderr.trim())
+        )
+    };
+
+    let unsigned_xdr = capture_command(&generate_build_command(args)).map_err(fail)?;
+    let simulated_xdr =
+        capture_command(&generate_simulate_command(args, &unsigned_xdr)).map_err(fail)?;
+
+    let envelope = transaction::decode_envelope(&simulated_xdr)?;
+    SimulationReport::from_envelope(&envelope, &simulated_xdr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test_contract".to_string(),
+            wasm_hash: "abc123".to_string(),
+            send: Some("yes".to_string()),
+            cost: true,
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    #[test]
+    fn test_should_simulate() {
+        let mut args = create_test_args();
+        assert!(should_simulate(&args));
+
+        args.skip_simulation = true;
+        assert!(!should_simulate(&args));
+        args.skip_simulation = false;
+
+        args.is_view = true;
+        assert!(!should_simulate(&args));
+        args.is_view = false;
+
+        args.build_only = true;
+        assert!(!should_simulate(&args));
+        args.build_only = false;
+
+        args.send = Some("no".to_string());
+        assert!(!should_simulate(&args));
+    }
+
+    #[test]
+    fn test_generate_build_command() {
+        let args = create_test_args();
+
+        let command = generate_build_command(&args);
+        assert!(command.contains("--build-only"));
+        assert!(!command.contains("--send"));
+        assert!(!command.contains("--cost"));
+        assert!(command.contains("-- upgrade --new_wasm_hash abc123"));
+    }
+
+    #[test]
+    fn test_generate_simulate_command() {
+        let args = create_test_args();
+
+        let command = generate_simulate_command(&args, "AAAAAgAAAAA=\n");
+        assert_eq!(
+            command,
+            "stellar tx simulate --source alice --network testnet AAAAAgAAAAA="
+        );
+    }
+
+    #[test]
+    fn test_generate_simulate_command_with_rpc() {
+        let args = UpgradeArgs {
+            rpc_url: Some("https://rpc.example.com".to_string()),
+            rpc_header: Some(vec!["Auth: Bearer token".to_string()]),
+            ..create_test_args()
+        };
+
+        let command = generate_simulate_command(&args, "AAAAAgAAAAA=");
+        assert_eq!(
+            command,
+            "stellar tx simulate --source alice --network testnet --rpc-url https://rpc.example.com --rpc-header 'Auth: Bearer token' AAAAAgAAAAA="
+        );
+    }
+
+    #[test]
+    fn test_report_from_envelope() {
+        let envelope: Value = serde_json::from_str(
+            r#"{"tx":{"tx":{
+                "fee":"1100",
+                "operations":[{"source_account":null,"body":{"invoke_host_function":{
+                    "host_function":{},
+                    "auth":[{"credentials":"source_account","root_invocation":{"function":{"contract_fn":{"contract_address":"CABC","function_name":"upgrade","args":[]}},"sub_invocations":[]}}]
+                }}}],
+                "ext":{"v1":{"ext":"v0","resources":{
+                    "footprint":{"read_only":[{"contract_code":{"hash":"abc123"}}],"read_write":[]},
+                    "instructions":1000,"read_bytes":300,"write_bytes":20
+                },"resource_fee":1000}}
+            },"signatures":[]}}"#,
+        )
+        .unwrap();
+
+        let report = SimulationReport::from_envelope(&envelope, "XDR\n").unwrap();
+        assert_eq!(report.fee, 1100);
+        assert_eq!(report.resource_fee, 1000);
+        assert_eq!(report.instructions, 1000);
+        assert_eq!(report.read_bytes, 300);
+        assert_eq!(report.write_bytes, 20);
+        assert_eq!(report.read_only, vec!["contract code abc123".to_string()]);
+        assert!(report.read_write.is_empty());
+        assert_eq!(report.auth.len(), 1);
+        assert_eq!(report.xdr, "XDR");
+    }
+
+    #[test]
+    fn test_report_from_envelope_without_resources() {
+        let envelope: Value = serde_json::from_str(
+            r#"{"tx":{"tx":{"fee":100,"operations":[{"body":{"invoke_host_function":{}}}],"ext":"v0"},"signatures":[]}}"#,
+        )
+        .unwrap();
+
+        assert!(SimulationReport::from_envelope(&envelope, "").is_err());
+    }
+
+    #[test]
+    fn test_describe_contract_errors() {
+        let errors = vec![ContractError {
+            enum_name: "Error".to_string(),
+            variant: "NotAuthorized".to_string(),
+            code: 1,
+        }];
+
+        let message = "HostError: Error(Contract, #1)\nEvent log: ...";
+        assert_eq!(
+            describe_contract_errors(message, &errors),
+            "HostError: Error(Contract, #1) (Error::NotAuthorized)\nEvent log: ..."
+        );
+
+        // Unknown codes are left untouched
+        let message = "HostError: Error(Contract, #7)";
+        assert_eq!(describe_contract_errors(message, &errors), message);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::config::Config;
+use crate::history::{self, HistoryEntry};
+use crate::security_checks::contract_info;
+use crate::security_checks::version_check::VersionCheck;
+use crate::security_checks::SecurityCheckContext;
+use crate::ttl::{self, EntryTtl, TtlTarget};
+use crate::{contract_id, ledger, StatusArgs, UpgradeArgs};
+use clap::Parser;
+use serde_json::Value;
+
+/// Approximate duration of a ledger, in seconds
+const LEDGER_SECONDS: u64 = 5;
+
+/// Current upgrade state of a deployed contract
+///
+/// Lookups that may fail independently are kept as results, so the status reports what it can.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractStatus {
+    pub contract_id: String,
+    pub alias: Option<String>,
+    pub network: String,
+    pub wasm_hash: String,
+    /// Contract metadata entries, `binver` included
+    pub meta: Result<Vec<(String, String)>, String>,
+    /// Signature of the deployed code's upgrade function
+    pub upgrade_function: Result<String, String>,
+    pub instance_ttl: Result<EntryTtl, String>,
+    pub last_upgrade: Result<Option<HistoryEntry>, String>,
+}
+
+impl ContractStatus {
+    /// Print the status
+    pub fn print(&self) {
+        let contract = match &self.alias {
+            Some(alias) => format!("{} ({})", alias, self.contract_id),
+            None => self.contract_id.clone(),
+        };
+
+        println!("Contract:      {} on {}", contract, self.network);
+        println!("WASM hash:     {}", self.wasm_hash);
+
+        match &self.meta {
+            Ok(meta) => {
+                let binver = meta.iter().find(|(key, _)| key == "binver");
+                match binver {
+                    Some((_, version)) => println!("Version:       {}", version),
+                    None => println!("Version:       ⚠️  no binver in the contract metadata"),
+                }
+                for (key, value) in meta.iter().filter(|(key, _)| key != "binver") {
+                    println!("  {}: {}", key, value);
+                }
+            }
+            Err(e) => println!("Version:       ❌ {}", e.trim()),
+        }
+
+        match &self.upgrade_function {
+            Ok(signature) => println!("Upgrade:       ✅ {}", signature),
+            Err(e) => println!(
+                "Upgrade:       ❌ {} - the contract can't be upgraded",
+                e.trim()
+            ),
+        }
+
+        match &self.instance_ttl {
+            Ok(ttl) if ttl.is_archived() => {
+                println!("Instance TTL:  ❌ archived, restore the instance before upgrading")
+            }
+            Ok(ttl) => {
+                let marker = if ttl.remaining() < u64::from(ttl::DEFAULT_MIN_TTL) {
+                    "⚠️ "
+                } else {
+                    "✅"
+                };
+                println!(
+                    "Instance TTL:  {} {} ledgers (about {} days), live until ledger {}",
+                    marker,
+                    ttl.remaining(),
+                    ttl.remaining() * LEDGER_SECONDS / 86_400,
+                    ttl.live_until
+                );
+            }
+            Err(e) => println!("Instance TTL:  ❌ {}", e.trim()),
+        }
+
+        match &self.last_upgrade {
+            Ok(Some(entry)) => {
+                println!("Last upgrade:");
+                entry.print();
+            }
+            Ok(None) => println!("Last upgrade:  none recorded in the history"),
+            Err(e) => println!("Last upgrade:  ❌ {}", e),
+        }
+    }
+}
+
+/// Parse the key and value of every entry of a `stellar contract info meta --output json`
+pub fn parse_meta_entries(metadata_json: &str) -> Result<Vec<(String, String)>, String> {
+    let json: Value = serde_json::from_str(metadata_json)
+        .map_err(|e| format!("Invalid contract metadata JSON: {}", e))?;
+    let entries = json
+        .as_array()
+        .ok_or_else(|| "Contract metadata is not a list".to_string())?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let meta = &entry["sc_meta_v0"];
+            Some((
+                meta["key"].as_str()?.to_string(),
+                meta["val"].as_str()?.to_string(),
+            ))
+        })
+        .collect())
+}
+
+/// Describe the upgrade function of a contract interface, as the upgrade function check finds it
+pub fn describe_upgrade_function(
+    interface: &str,
+    upgrade_fn: Option<&str>,
+    hash_arg: Option<&str>,
+) -> Result<String, String> {
+    let functions = contract_info::parse_functions(interface);
+    contract_info::find_upgrade_entrypoint(&functions, upgrade_fn, hash_arg)
+        .map(|entrypoint| entrypoint.signature())
+}
+
+/// Upgrade arguments targeting the deployed code, to reuse the upgrade's lookups on it
+fn deployed_args(args: &StatusArgs, id: &str, wasm_hash: &str) -> Result<UpgradeArgs, String> {
+    let mut argv = vec![
+        "upgrade".to_string(),
+        "--id".to_string(),
+        id.to_string(),
+        "--wasm-hash".to_string(),
+        wasm_hash.to_string(),
+        "--network".to_string(),
+        args.network.clone(),
+    ];
+    let options = [
+        ("--upgrade-fn", &args.upgrade_fn),
+        ("--hash-arg", &args.hash_arg),
+    ];
+    for (flag, value) in options {
+        if let Some(value) = value {
+            argv.extend([flag.to_string(), value.clone()]);
+        }
+    }
+
+    UpgradeArgs::try_parse_from(argv).map_err(|e| e.to_string().trim().to_string())
+}
+
+/// Gather the current upgrade state of the contract
+pub fn fetch_status(args: &StatusArgs) -> Result<ContractStatus, String> {
+    let (id, alias) = contract_id::resolve_contract_id(
+        &args.id,
+        &args.network,
+        &Config::load()?,
+        contract_id::show_cli_alias,
+    )?;
+
+    let instance = ledger::fetch_contract_instance(&id, &format!("--network {}", args.network))?;
+    let wasm_hash = ledger::extract_executable_hash(&instance)?;
+    let deployed = deployed_args(args, &id, &wasm_hash)?;
+
+    let version_check = VersionCheck::new();
+    let meta = version_check
+        .get_contract_metadata(&deployed, None)
+        .and_then(|metadata| parse_meta_entries(&metadata));
+
+    let mut context = SecurityCheckContext::new();
+    let upgrade_function = contract_info::fetch_contract_interface(&deployed, &mut context)
+        .and_then(|()| {
+            describe_upgrade_function(
+                context.contract_interface.as_deref().unwrap_or_default(),
+                deployed.upgrade_fn.as_deref(),
+                deployed.hash_arg.as_deref(),
+            )
+        });
+
+    let last_upgrade = history::history_path()
+        .and_then(|path| history::read(&path))
+        .map(|entries| history::last_upgrade(&entries, &id, &args.network));
+
+    Ok(ContractStatus {
+        contract_id: id,
+        alias,
+        network: args.network.clone(),
+        wasm_hash,
+        meta,
+        upgrade_function,
+        instance_ttl: ttl::parse_entry_ttl(TtlTarget::Instance, &instance),
+        last_upgrade,
+    })
+}
+
+/// Run the status command
+pub fn run_status(args: &StatusArgs) -> Result<(), String> {
+    let status = fetch_status(args)?;
+    println!();
+    status.print();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_args() -> StatusArgs {
+        StatusArgs {
+            id: "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM".to_string(),
+            network: "testnet".to_string(),
+            upgrade_fn: None,
+            hash_arg: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_meta_entries() {
+        let metadata = r#"[{"sc_meta_v0":{"key":"binver","val":"2.0.0"}},{"sc_meta_v0":{"key":"rsver","val":"1.85.0"}}]"#;
+        assert_eq!(
+            parse_meta_entries(metadata).unwrap(),
+            vec![
+                ("binver".to_string(), "2.0.0".to_string()),
+                ("rsver".to_string(), "1.85.0".to_string()),
+            ]
+        );
+
+        assert!(parse_meta_entries("[]").unwrap().is_empty());
+        assert!(parse_meta_entries("{}").is_err());
+    }
+
+    #[test]
+    fn test_describe_upgrade_function() {
+        let interface = r#"
+        pub trait Contract {
+            fn upgrade(env: soroban_sdk::Env, new_wasm_hash: soroban_sdk::BytesN<32>, operator: soroban_sdk::Address);
+            fn balance(env: soroban_sdk::Env, id: soroban_sdk::Address) -> i128;
+        }
+        "#;
+
+        let signature = describe_upgrade_function(interface, None, None).unwrap();
+        assert!(signature.starts_with("upgrade(new_wasm_hash: BytesN<32>, operator: "));
+
+        assert!(describe_upgrade_function(interface, Some("set_code"), None).is_err());
+    }
+
+    #[test]
+    fn test_deployed_args() {
+        let mut args = create_test_args();
+        args.upgrade_fn = Some("set_code".to_string());
+        let hash = "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff";
+
+        let deployed = deployed_args(&args, &args.id, hash).unwrap();
+        assert_eq!(deployed.id, args.id);
+        assert_eq!(deployed.wasm_hash, hash);
+        assert_eq!(deployed.upgrade_fn.as_deref(), Some("set_code"));
+    }
+}
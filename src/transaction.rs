@@ -0,0 +1,546 @@
+use crate::capture_command;
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// An authorization the transaction requires to succeed
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthRequirement {
+    /// Address that must sign, or `None` when the transaction source account authorizes it
+    pub signer: Option<String>,
+    pub contract: String,
+    pub function: String,
+}
+
+/// Decode a base64 transaction envelope into its JSON representation
+pub fn decode_envelope(xdr: &str) -> Result<Value, String> {
+    let command = format!(
+        "echo {} | stellar xdr decode --type TransactionEnvelope --output json",
+        xdr.trim()
+    );
+    let json = capture_command(&command)
+        .map_err(|e| format!("Failed to decode transaction envelope: {}", e))?;
+
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse transaction envelope: {}", e))
+}
+
+/// Encode the JSON representation of a transaction envelope into base64 XDR
+pub fn encode_envelope(envelope: &Value) -> Result<String, String> {
+    // The JSON is piped rather than passed through the shell, which would need it quoted
+    let mut child = Command::new("stellar")
+        .args(["xdr", "encode", "--type", "TransactionEnvelope"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| "Failed to open stdin of stellar xdr encode".to_string())?
+        .write_all(envelope.to_string().as_bytes())
+        .map_err(|e| format!("Failed to write the transaction envelope: {}", e))?;
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to encode transaction envelope: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    String::from_utf8(output.stdout)
+        .map(|xdr| xdr.trim().to_string())
+        .map_err(|_| "Failed to parse command output".to_string())
+}
+
+/// Read a JSON number that may be encoded as a string (64-bit XDR integers are)
+pub fn as_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Get the transaction inside an envelope
+pub fn transaction(envelope: &Value) -> Result<&Value, String> {
+    envelope
+        .get("tx")
+        .and_then(|v1| v1.get("tx"))
+        .ok_or_else(|| "Transaction envelope is not a v1 transaction".to_string())
+}
+
+/// Get the transaction inside an envelope, to edit it
+pub fn transaction_mut(envelope: &mut Value) -> Result<&mut Value, String> {
+    envelope
+        .get_mut("tx")
+        .and_then(|v1| v1.get_mut("tx"))
+        .ok_or_else(|| "Transaction envelope is not a v1 transaction".to_string())
+}
+
+/// Time and ledger bounds of a transaction, `None` when unbounded
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bounds {
+    /// Unix timestamps
+    pub min_time: Option<u64>,
+    pub max_time: Option<u64>,
+    pub min_ledger: Option<u64>,
+    pub max_ledger: Option<u64>,
+}
+
+/// Read the time and ledger bounds from the preconditions of a transaction
+pub fn bounds(tx: &Value) -> Bounds {
+    let cond = &tx["cond"];
+    let (time, ledger) = match cond.get("v2") {
+        Some(v2) => (&v2["time_bounds"], &v2["ledger_bounds"]),
+        None => (cond.get("time").unwrap_or(&Value::Null), &Value::Null),
+    };
+    // A bound of 0 leaves that side of the range open
+    let bound = |value: &Value| as_u64(value).filter(|bound| *bound > 0);
+
+    Bounds {
+        min_time: bound(&time["min_time"]),
+        max_time: bound(&time["max_time"]),
+        min_ledger: bound(&ledger["min_ledger"]),
+        max_ledger: bound(&ledger["max_ledger"]),
+    }
+}
+
+/// Write time and ledger bounds to the preconditions of a transaction
+///
+/// Only time bounds fit the legacy `time` preconditions, ledger bounds need `v2` ones.
+pub fn set_bounds(tx: &mut Value, bounds: &Bounds) {
+    let bound = |bound: Option<u64>| bound.unwrap_or(0);
+    let time = serde_json::json!({
+        "min_time": bound(bounds.min_time).to_string(),
+        "max_time": bound(bounds.max_time).to_string(),
+    });
+    let has_time = bounds.min_time.is_some() || bounds.max_time.is_some();
+
+    tx["cond"] = if bounds.min_ledger.is_some() || bounds.max_ledger.is_some() {
+        serde_json::json!({ "v2": {
+            "time_bounds": if has_time { time } else { Value::Null },
+            "ledger_bounds": {
+                "min_ledger": bound(bounds.min_ledger),
+                "max_ledger": bound(bounds.max_ledger),
+            },
+            "min_seq_num": null,
+            "min_seq_age": "0",
+            "min_seq_ledger_gap": 0,
+            "extra_signers": [],
+        } })
+    } else if has_time {
+        serde_json::json!({ "time": time })
+    } else {
+        Value::from("none")
+    };
+}
+
+/// Unix timestamps start at this value, smaller `--not-before` values are ledger sequences
+pub const MIN_TIMESTAMP: u64 = 1_000_000_000;
+
+/// Earliest point a transaction can be submitted at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotBefore {
+    Ledger(u64),
+    /// Unix timestamp
+    Time(u64),
+}
+
+impl NotBefore {
+    /// Interpret a `--not-before` value as a ledger sequence or a Unix timestamp
+    pub fn from_value(value: u64) -> Self {
+        if value >= MIN_TIMESTAMP {
+            NotBefore::Time(value)
+        } else {
+            NotBefore::Ledger(value)
+        }
+    }
+
+    /// Lower bounds of a transaction
+    pub fn from_bounds(bounds: &Bounds) -> Vec<Self> {
+        let ledger = bounds.min_ledger.map(NotBefore::Ledger);
+        let time = bounds.min_time.map(NotBefore::Time);
+        ledger.into_iter().chain(time).collect()
+    }
+
+    /// Whether the bound has been reached at `ledger` and Unix timestamp `time`
+    pub fn is_reached(&self, ledger: u64, time: u64) -> bool {
+        match *self {
+            NotBefore::Ledger(min_ledger) => ledger >= min_ledger,
+            NotBefore::Time(min_time) => time >= min_time,
+        }
+    }
+
+    /// Add the bound to the preconditions of a transaction
+    pub fn apply(&self, tx: &mut Value) -> Result<(), String> {
+        let mut bounds = bounds(tx);
+        let (value, min, max) = match *self {
+            NotBefore::Ledger(ledger) => (ledger, &mut bounds.min_ledger, bounds.max_ledger),
+            NotBefore::Time(time) => (time, &mut bounds.min_time, bounds.max_time),
+        };
+        if let Some(max) = max.filter(|max| *max < value) {
+            return Err(format!(
+                "❌ Not before {} is after the transaction's upper bound {}",
+                self, max
+            ));
+        }
+        *min = Some(value);
+
+        set_bounds(tx, &bounds);
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for NotBefore {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NotBefore::Ledger(ledger) => write!(f, "ledger {}", ledger),
+            NotBefore::Time(time) => write!(f, "{}", crate::history::format_timestamp(*time)),
+        }
+    }
+}
+
+/// Get the single `InvokeHostFunction` operation of a Soroban transaction
+pub fn invoke_host_function(tx: &Value) -> Result<&Value, String> {
+    let operations = tx
+        .get("operations")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "Transaction has no operations".to_string())?;
+
+    if operations.len() != 1 {
+        return Err(format!(
+            "Expected a single operation, found {}",
+            operations.len()
+        ));
+    }
+
+    operations[0]
+        .get("body")
+        .and_then(|body| body.get("invoke_host_function"))
+        .ok_or_else(|| "Operation is not an InvokeHostFunction".to_string())
+}
+
+/// Describe a footprint ledger key in a compact, human readable form
+pub fn describe_ledger_key(key: &Value) -> String {
+    if let Some(code) = key.get("contract_code") {
+        return format!("contract code {}", code["hash"].as_str().unwrap_or("?"));
+    }
+
+    if let Some(data) = key.get("contract_data") {
+        let storage_key = match &data["key"] {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        return format!(
+            "contract data {} {} ({})",
+            data["contract"].as_str().unwrap_or("?"),
+            storage_key,
+            data["durability"].as_str().unwrap_or("?")
+        );
+    }
+
+    if let Some(account) = key.get("account") {
+        return format!("account {}", account["account_id"].as_str().unwrap_or("?"));
+    }
+
+    key.to_string()
+}
+
+/// List the authorizations recorded on an `InvokeHostFunction` operation
+pub fn auth_requirements(invoke: &Value) -> Vec<AuthRequirement> {
+    let entries = match invoke.get("auth").and_then(Value::as_array) {
+        Some(entries) => entries,
+        None => return Vec::new(),
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let signer = entry["credentials"]
+                .get("address")
+                .and_then(|credentials| credentials["address"].as_str())
+                .map(str::to_string);
+            let function = &entry["root_invocation"]["function"]["contract_fn"];
+
+            AuthRequirement {
+                signer,
+                contract: function["contract_address"]
+                    .as_str()
+                    .unwrap_or("?")
+                    .to_string(),
+                function: function["function_name"]
+                    .as_str()
+                    .unwrap_or("?")
+                    .to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIMULATED_UPGRADE: &str = r#"{
+        "tx": {
+            "tx": {
+                "source_account": "GBZXN7PIRZGNMHGA7MUUUF4GWPY5AYPV6LY4UV2GL6VJGIQRXFDNMADI",
+                "fee": 123456,
+                "seq_num": "4294967297",
+                "cond": "none",
+                "memo": "none",
+                "operations": [{
+                    "source_account": null,
+                    "body": {
+                        "invoke_host_function": {
+                            "host_function": {
+                                "invoke_contract": {
+                                    "contract_address": "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX",
+                                    "function_name": "upgrade",
+                                    "args": [{"bytes": "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"}]
+                                }
+                            },
+                            "auth": [{
+                                "credentials": {
+                                    "address": {
+                                        "address": "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54",
+                                        "nonce": "1",
+                                        "signature_expiration_ledger": 0,
+                                        "signature": "void"
+                                    }
+                                },
+                                "root_invocation": {
+                                    "function": {
+                                        "contract_fn": {
+                                            "contract_address": "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX",
+                                            "function_name": "upgrade",
+                                            "args": []
+                                        }
+                                    },
+                                    "sub_invocations": []
+                                }
+                            }]
+                        }
+                    }
+                }],
+                "ext": {
+                    "v1": {
+                        "ext": "v0",
+                        "resources": {
+                            "footprint": {
+                                "read_only": [
+                                    {"contract_code": {"hash": "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"}}
+                                ],
+                                "read_write": [
+                                    {"contract_data": {
+                                        "contract": "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX",
+                                        "key": "ledger_key_contract_instance",
+                                        "durability": "persistent"
+                                    }}
+                                ]
+                            },
+                            "instructions": 1500000,
+                            "disk_read_bytes": 7000,
+                            "write_bytes": 200
+                        },
+                        "resource_fee": "123356"
+                    }
+                }
+            },
+            "signatures": []
+        }
+    }"#;
+
+    #[test]
+    fn test_as_u64() {
+        assert_eq!(as_u64(&Value::from(42)), Some(42));
+        assert_eq!(as_u64(&Value::from("4294967297")), Some(4294967297));
+        assert_eq!(as_u64(&Value::from("abc")), None);
+        assert_eq!(as_u64(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(
+            bounds(&serde_json::json!({ "cond": "none" })),
+            Bounds::default()
+        );
+
+        let tx = serde_json::json!({ "cond": { "time": { "min_time": "1700000000", "max_time": "0" } } });
+        assert_eq!(
+            bounds(&tx),
+            Bounds {
+                min_time: Some(1_700_000_000),
+                ..Bounds::default()
+            }
+        );
+
+        let tx = serde_json::json!({ "cond": { "v2": {
+            "time_bounds": null,
+            "ledger_bounds": { "min_ledger": 5000, "max_ledger": 6000 },
+            "min_seq_num": null
+        } } });
+        assert_eq!(
+            bounds(&tx),
+            Bounds {
+                min_ledger: Some(5000),
+                max_ledger: Some(6000),
+                ..Bounds::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_bounds() {
+        let mut tx = serde_json::json!({ "cond": "none" });
+
+        let time = Bounds {
+            min_time: Some(1_700_000_000),
+            ..Bounds::default()
+        };
+        set_bounds(&mut tx, &time);
+        assert_eq!(tx["cond"]["time"]["min_time"], "1700000000");
+        assert_eq!(bounds(&tx), time);
+
+        let both = Bounds {
+            min_ledger: Some(5000),
+            ..time.clone()
+        };
+        set_bounds(&mut tx, &both);
+        assert!(tx["cond"]["v2"].is_object());
+        assert_eq!(bounds(&tx), both);
+
+        set_bounds(&mut tx, &Bounds::default());
+        assert_eq!(tx["cond"], "none");
+    }
+
+    #[test]
+    fn test_not_before() {
+        assert_eq!(
+            NotBefore::from_value(55_000_000),
+            NotBefore::Ledger(55_000_000)
+        );
+        assert_eq!(
+            NotBefore::from_value(1_700_000_000),
+            NotBefore::Time(1_700_000_000)
+        );
+
+        assert!(NotBefore::Ledger(5000).is_reached(5000, 0));
+        assert!(!NotBefore::Ledger(5000).is_reached(4999, u64::MAX));
+        assert!(!NotBefore::Time(1_700_000_000).is_reached(u64::MAX, 1_699_999_999));
+
+        let mut tx = serde_json::json!({ "cond": { "time": { "min_time": "0", "max_time": "1700000300" } } });
+        NotBefore::Time(1_700_000_000).apply(&mut tx).unwrap();
+        let applied = bounds(&tx);
+        assert_eq!(applied.min_time, Some(1_700_000_000));
+        assert_eq!(applied.max_time, Some(1_700_000_300));
+        assert_eq!(
+            NotBefore::from_bounds(&applied),
+            vec![NotBefore::Time(1_700_000_000)]
+        );
+
+        // The lower bound can't exceed an existing upper bound
+        assert!(NotBefore::Time(1_800_000_000).apply(&mut tx).is_err());
+
+        NotBefore::Ledger(5000).apply(&mut tx).unwrap();
+        assert_eq!(bounds(&tx).min_ledger, Some(5000));
+        assert_eq!(bounds(&tx).min_time, Some(1_700_000_000));
+        assert_eq!(
+            NotBefore::from_bounds(&bounds(&tx)),
+            vec![NotBefore::Ledger(5000), NotBefore::Time(1_700_000_000)]
+        );
+    }
+
+    #[test]
+    fn test_invoke_host_function() {
+        let envelope: Value = serde_json::from_str(SIMULATED_UPGRADE).unwrap();
+        let tx = transaction(&envelope).unwrap();
+        let invoke = invoke_host_function(tx).unwrap();
+
+        assert_eq!(
+            invoke["host_function"]["invoke_contract"]["function_name"],
+            "upgrade"
+        );
+    }
+
+    #[test]
+    fn test_transaction_mut() {
+        let mut envelope: Value = serde_json::from_str(SIMULATED_UPGRADE).unwrap();
+        NotBefore::Ledger(5000)
+            .apply(transaction_mut(&mut envelope).unwrap())
+            .unwrap();
+        assert_eq!(
+            bounds(transaction(&envelope).unwrap()).min_ledger,
+            Some(5000)
+        );
+
+        let mut envelope = serde_json::json!({ "tx_v0": { "tx": {} } });
+        assert!(transaction_mut(&mut envelope).is_err());
+    }
+
+    #[test]
+    fn test_invoke_host_function_wrong_operation() {
+        let tx: Value = serde_json::from_str(
+            r#"{"operations":[{"source_account":null,"body":{"payment":{}}}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            invoke_host_function(&tx).unwrap_err(),
+            "Operation is not an InvokeHostFunction"
+        );
+    }
+
+    #[test]
+    fn test_describe_ledger_key() {
+        let key: Value = serde_json::from_str(r#"{"contract_code":{"hash":"abc123"}}"#).unwrap();
+        assert_eq!(describe_ledger_key(&key), "contract code abc123");
+
+        let key: Value = serde_json::from_str(
+            r#"{"contract_data":{"contract":"CABC","key":"ledger_key_contract_instance","durability":"persistent"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            describe_ledger_key(&key),
+            "contract data CABC ledger_key_contract_instance (persistent)"
+        );
+
+        let key: Value = serde_json::from_str(r#"{"account":{"account_id":"GABC"}}"#).unwrap();
+        assert_eq!(describe_ledger_key(&key), "account GABC");
+    }
+
+    #[test]
+    fn test_auth_requirements() {
+        let envelope: Value = serde_json::from_str(SIMULATED_UPGRADE).unwrap();
+        let invoke = invoke_host_function(transaction(&envelope).unwrap()).unwrap();
+
+        let auth = auth_requirements(invoke);
+        assert_eq!(
+            auth,
+            vec![AuthRequirement {
+                signer: Some(
+                    "GDAT5HWTGIU4TSSZ4752OUC4SABDLTLZFRPZUJ3D6LKBNEPA7V2CIG54".to_string()
+                ),
+                contract: "CABY2EPFRLWMDTOQJMOSKM2LPZZ22LUKD5LE2MW35PY3T7FURARQDGMX".to_string(),
+                function: "upgrade".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_auth_requirements_source_account() {
+        let invoke: Value = serde_json::from_str(
+            r#"{"auth":[{"credentials":"source_account","root_invocation":{"function":{"contract_fn":{"contract_address":"CABC","function_name":"upgrade","args":[]}},"sub_invocations":[]}}]}"#,
+        )
+        .unwrap();
+
+        let auth = auth_requirements(&invoke);
+        assert_eq!(auth.len(), 1);
+        assert_eq!(auth[0].signer, None);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::transaction::as_u64;
+use crate::{capture_command, is_submitted, network_flags, UpgradeArgs};
+use crate::{ledger, wasm};
+use serde_json::Value;
+
+/// Default minimum remaining TTL, about 30 days of 5 second ledgers
+pub const DEFAULT_MIN_TTL: u32 = 518_400;
+
+/// Ledger entry whose TTL matters to the upgrade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TtlTarget {
+    /// The contract instance being upgraded
+    Instance,
+    /// The code entry of the new WASM
+    Code,
+}
+
+impl TtlTarget {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TtlTarget::Instance => "contract instance",
+            TtlTarget::Code => "new WASM code entry",
+        }
+    }
+}
+
+/// TTL of a ledger entry, as of the latest ledger
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryTtl {
+    pub target: TtlTarget,
+    pub live_until: u64,
+    pub latest_ledger: u64,
+}
+
+impl EntryTtl {
+    /// Whether the entry is archived and must be restored before use
+    pub fn is_archived(&self) -> bool {
+        self.live_until < self.latest_ledger
+    }
+
+    /// Number of ledgers left before the entry is archived
+    pub fn remaining(&self) -> u64 {
+        self.live_until.saturating_sub(self.latest_ledger)
+    }
+}
+
+/// Parse the TTL of the entry in a `stellar ledger entry fetch` JSON
+pub fn parse_entry_ttl(target: TtlTarget, entry_json: &str) -> Result<EntryTtl, String> {
+    let json: Value = serde_json::from_str(entry_json)
+        .map_err(|e| format!("Invalid ledger entry JSON: {}", e))?;
+    let entry = json["entries"]
+        .get(0)
+        .ok_or_else(|| format!("The {} was not found", target.describe()))?;
+
+    let live_until = as_u64(&entry["live_until_ledger_seq"])
+        .ok_or_else(|| format!("The {} has no live-until ledger", target.describe()))?;
+    let latest_ledger = as_u64(&json["latest_ledger"])
+        .ok_or_else(|| "Latest ledger missing from the ledger entry response".to_string())?;
+
+    Ok(EntryTtl {
+        target,
+        live_until,
+        latest_ledger,
+    })
+}
+
+/// Fetch the TTL of the contract instance and of the new WASM code entry
+pub fn fetch_ttls(args: &UpgradeArgs) -> Result<Vec<EntryTtl>, String> {
+    let instance = ledger::fetch_contract_instance(&args.id, &network_flags(args))?;
+    let code = ledger::fetch_contract_code(&args.wasm_hash, &network_flags(args))?;
+
+    let mut ttls = vec![parse_entry_ttl(TtlTarget::Instance, &instance)?];
+    // A WASM still to be uploaded from --wasm starts with a fresh TTL
+    if args.wasm.is_none() || wasm::has_code_entry(&code)? {
+        ttls.push(parse_entry_ttl(TtlTarget::Code, &code)?);
+    }
+    Ok(ttls)
+}
+
+/// Whether short-lived entries must be extended before the upgrade
+pub fn should_extend(args: &UpgradeArgs) -> bool {
+    args.extend_ttl && is_submitted(args)
+}
+
+/// Generate the command extending the TTL of an entry to at least `--min-ttl` ledgers
+pub fn generate_extend_command(args: &UpgradeArgs, target: TtlTarget) -> String {
+    let entry = match target {
+        TtlTarget::Instance => format!("--id {} --durability persistent", args.id),
+        TtlTarget::Code => format!("--wasm-hash {}", args.wasm_hash),
+    };
+
+    format!(
+        "stellar contract extend {} --ledgers-to-extend {} --source {} {}",
+        entry,
+        args.min_ttl,
+        args.source,
+        network_flags(args)
+    )
+}
+
+/// Extend the entries whose TTL is below `--min-ttl`
+pub fn extend_ttls(args: &UpgradeArgs) -> Result<(), String> {
+    for ttl in fetch_ttls(args)? {
+        if ttl.is_archived() || ttl.remaining() >= u64::from(args.min_ttl) {
+            continue;
+        }
+
+        let command = generate_extend_command(args, ttl.target);
+        println!("Extending: {}", command);
+        capture_command(&command).map_err(|e| {
+            format!(
+                "❌ Failed to extend the TTL of the {}:\n{}",
+                ttl.target.describe(),
+                e.trim()
+            )
+        })?;
+        println!(
+            "✅ Extended the {} to live for at least {} ledgers",
+            ttl.target.describe(),
+            args.min_ttl
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test_contract".to_string(),
+            wasm_hash: "test_hash".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    #[test]
+    fn test_parse_entry_ttl() {
+        let entry = r#"{"latest_ledger":1000,"entries":[{"key":{},"val":{},"last_modified_ledger":900,"live_until_ledger_seq":5000}]}"#;
+
+        let ttl = parse_entry_ttl(TtlTarget::Instance, entry).unwrap();
+        assert_eq!(ttl.live_until, 5000);
+        assert_eq!(ttl.latest_ledger, 1000);
+        assert_eq!(ttl.remaining(), 4000);
+        assert!(!ttl.is_archived());
+    }
+
+    #[test]
+    fn test_parse_entry_ttl_archived() {
+        let entry = r#"{"latest_ledger":"6000","entries":[{"live_until_ledger_seq":"5000"}]}"#;
+
+        let ttl = parse_entry_ttl(TtlTarget::Code, entry).unwrap();
+        assert!(ttl.is_archived());
+        assert_eq!(ttl.remaining(), 0);
+    }
+
+    #[test]
+    fn test_parse_entry_ttl_missing() {
+        let result = parse_entry_ttl(TtlTarget::Code, r#"{"latest_ledger":1000,"entries":[]}"#);
+        assert!(result.unwrap_err().contains("new WASM code entry"));
+    }
+
+    #[test]
+    fn test_generate_extend_command() {
+        let mut args = create_test_args();
+        args.min_ttl = 1000;
+
+        assert_eq!(
+            generate_extend_command(&args, TtlTarget::Instance),
+            "stellar contract extend --id test_contract --durability persistent --ledgers-to-extend 1000 --source alice --network testnet"
+        );
+        assert_eq!(
+            generate_extend_command(&args, TtlTarget::Code),
+            "stellar contract extend --wasm-hash test_hash --ledgers-to-extend 1000 --source alice --network testnet"
+        );
+    }
+
+    #[test]
+    fn test_should_extend() {
+        let mut args = create_test_args();
+        assert!(!should_extend(&args));
+
+        args.extend_ttl = true;
+        assert!(should_extend(&args));
+
+        args.build_only = true;
+        assert!(!should_extend(&args));
+    }
+}
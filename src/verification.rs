@@ -0,0 +1,162 @@
+use crate::security_checks::version_check::VersionCheck;
+use crate::{capture_command, contract_id, is_submitted, ledger, network_flags, UpgradeArgs};
+
+/// Whether the upgrade transaction was actually submitted and can be verified on-chain
+pub fn should_verify(args: &UpgradeArgs) -> bool {
+    !args.no_verify && is_submitted(args)
+}
+
+/// Generate the command simulating a read-only smoke-test invocation
+pub fn generate_verify_call_command(args: &UpgradeArgs, call: &str) -> String {
+    format!(
+        "stellar contract invoke --id {} --source {} {} --send no -- {}",
+        args.id,
+        args.source,
+        network_flags(args),
+        call
+    )
+}
+
+/// Confirm the executable of the contract instance is the new WASM hash
+fn verify_executable(args: &UpgradeArgs) -> Result<(), String> {
+    let current_hash = ledger::fetch_contract_wasm_hash(&args.id, &network_flags(args))?;
+
+    if current_hash.eq_ignore_ascii_case(&args.wasm_hash) {
+        println!("✅ Contract executable points to the new WASM hash");
+        Ok(())
+    } else {
+        Err(format!(
+            "Contract executable points to {} instead of {}",
+            current_hash, args.wasm_hash
+        ))
+    }
+}
+
+/// Confirm the deployed `binver` matches the one of the new WASM
+fn verify_binver(args: &UpgradeArgs) -> Result<(), String> {
+    let version_check = VersionCheck::new();
+
+    let deployed_metadata = version_check.get_contract_metadata(args, None)?;
+    let deployed_version = version_check.extract_binver(&deployed_metadata)?;
+
+    let new_metadata = version_check.get_contract_metadata(args, Some(&args.wasm_hash))?;
+    let new_version = version_check.extract_binver(&new_metadata)?;
+
+    if deployed_version == new_version {
+        println!("✅ Deployed version is now {}", deployed_version);
+        Ok(())
+    } else {
+        Err(format!(
+            "Deployed version is {} but the new WASM declares {}",
+            deployed_version, new_version
+        ))
+    }
+}
+
+/// Simulate a read-only smoke-test invocation against the upgraded contract
+fn verify_call(args: &UpgradeArgs, call: &str) -> Result<(), String> {
+    let command = generate_verify_call_command(args, call);
+
+    match capture_command(&command) {
+        Ok(output) => {
+            println!("✅ Smoke test `{}` returned: {}", call, output.trim());
+            Ok(())
+        }
+        Err(e) => Err(format!("Smoke test `{}` failed: {}", call, e.trim())),
+    }
+}
+
+/// Verify the upgrade took effect, reporting every mismatch found
+pub fn verify_upgrade(args: &UpgradeArgs) -> Result<(), String> {
+    println!("\nVerifying upgrade...");
+
+    let mut failures = Vec::new();
+
+    if let Err(e) = verify_executable(args) {
+        failures.push(e);
+    }
+
+    if let Err(e) = verify_binver(args) {
+        failures.push(e);
+    }
+
+    for call in &args.verify_calls {
+        if let Err(e) = verify_call(args, call) {
+            failures.push(e);
+        }
+    }
+
+    if failures.is_empty() {
+        println!(
+            "✅ Upgrade of {} verified",
+            contract_id::display_contract(args)
+        );
+        Ok(())
+    } else {
+        Err(format!(
+            "❌ Upgrade transaction was submitted but verification failed:\n  - {}",
+            failures.join("\n  - ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test_contract".to_string(),
+            wasm_hash: "test_hash".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    #[test]
+    fn test_should_verify() {
+        let mut args = create_test_args();
+        assert!(should_verify(&args));
+
+        args.send = Some("default".to_string());
+        assert!(should_verify(&args));
+
+        args.send = Some("no".to_string());
+        assert!(!should_verify(&args));
+        args.send = None;
+
+        args.is_view = true;
+        assert!(!should_verify(&args));
+        args.is_view = false;
+
+        args.build_only = true;
+        assert!(!should_verify(&args));
+        args.build_only = false;
+
+        args.no_verify = true;
+        assert!(!should_verify(&args));
+    }
+
+    #[test]
+    fn test_generate_verify_call_command() {
+        let args = create_test_args();
+
+        let command = generate_verify_call_command(&args, "balance --id GABC");
+        assert_eq!(
+            command,
+            "stellar contract invoke --id test_contract --source alice --network testnet --send no -- balance --id GABC"
+        );
+    }
+
+    #[test]
+    fn test_verify_upgrade_reports_failure() {
+        let mut args = create_test_args();
+        args.verify_calls = vec!["version".to_string()];
+
+        // Without a reachable network every verification step fails
+        let result = verify_upgrade(&args);
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .contains("Upgrade transaction was submitted but verification failed"));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{capture_command, is_submitted, ledger, network_flags, UpgradeArgs};
+use regex::Regex;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Whether a code ledger entry JSON contains the uploaded WASM
+pub fn has_code_entry(code_json: &str) -> Result<bool, String> {
+    let json: Value =
+        serde_json::from_str(code_json).map_err(|e| format!("Invalid ledger entry JSON: {}", e))?;
+
+    Ok(json["entries"]
+        .as_array()
+        .is_some_and(|entries| !entries.is_empty()))
+}
+
+/// SHA-256 of a WASM binary as lowercase hex, the hash the network identifies it by
+pub fn hash_wasm(wasm: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(wasm))
+}
+
+/// Check the local WASM file is the one identified by `--wasm-hash`
+pub fn verify_wasm_file(path: &str, wasm_hash: &str) -> Result<(), String> {
+    let wasm = std::fs::read(path).map_err(|e| format!("❌ Failed to read {}: {}", path, e))?;
+    let hash = hash_wasm(&wasm);
+
+    if hash.eq_ignore_ascii_case(wasm_hash) {
+        Ok(())
+    } else {
+        Err(format!(
+            "❌ {} has SHA-256 {}, which doesn't match --wasm-hash {}",
+            path, hash, wasm_hash
+        ))
+    }
+}
+
+/// Whether the new WASM is installed on the network
+pub fn is_installed(args: &UpgradeArgs) -> Result<bool, String> {
+    let code = ledger::fetch_contract_code(&args.wasm_hash, &network_flags(args))?;
+    has_code_entry(&code)
+}
+
+/// Arguments of `stellar contract info` locating the WASM `wasm_hash`
+///
+/// The `--wasm` file is read when it is that WASM, as it may only be uploaded after confirmation.
+pub fn code_source(args: &UpgradeArgs, wasm_hash: &str) -> String {
+    match &args.wasm {
+        Some(path) if wasm_hash == args.wasm_hash => format!("--wasm {}", path),
+        _ => format!("--wasm-hash {} {}", wasm_hash, network_flags(args)),
+    }
+}
+
+/// Check the new WASM is installed, or given with `--wasm` to upload once the upgrade is confirmed
+///
+/// Returns whether the WASM is already installed.
+pub fn check_installed(args: &UpgradeArgs) -> Result<bool, String> {
+    if let Some(path) = &args.wasm {
+        verify_wasm_file(path, &args.wasm_hash)?;
+    }
+
+    if is_installed(args)? {
+        println!(
+            "✅ WASM {} is installed on {}",
+            args.wasm_hash, args.network
+        );
+        return Ok(true);
+    }
+
+    match &args.wasm {
+        Some(path) => {
+            println!(
+                "⚠️  WASM {} is not installed on {}, {} will be uploaded before upgrading",
+                args.wasm_hash, args.network, path
+            );
+            Ok(false)
+        }
+        None => Err(format!(
+            "❌ WASM {} is not installed on {}. Upload it with `stellar contract upload`, or pass the WASM file with --wasm to upload it first.",
+            args.wasm_hash, args.network
+        )),
+    }
+}
+
+/// Generate the command uploading a WASM file
+pub fn generate_upload_command(args: &UpgradeArgs, path: &str) -> String {
+    format!(
+        "stellar contract upload --wasm {} --source {} {}",
+        path,
+        args.source,
+        network_flags(args)
+    )
+}
+
+/// Extract the WASM hash from `stellar contract upload` output
+pub fn extract_uploaded_hash(output: &str) -> Result<String, String> {
+    let hash = Regex::new(r"\b[0-9a-fA-F]{64}\b").unwrap();
+    hash.find_iter(output)
+        .last()
+        .map(|m| m.as_str().to_lowercase())
+        .ok_or_else(|| "WASM hash not found in upload output".to_string())
+}
+
+/// Upload the WASM file given with `--wasm`, checking it is the WASM to upgrade to
+pub fn upload(args: &UpgradeArgs, path: &str) -> Result<(), String> {
+    let command = generate_upload_command(args, path);
+    println!("Uploading: {}", command);
+
+    let output = capture_command(&command)
+        .map_err(|e| format!("❌ Failed to upload {}:\n{}", path, e.trim()))?;
+    let uploaded = extract_uploaded_hash(&output)?;
+
+    if !uploaded.eq_ignore_ascii_case(&args.wasm_hash) {
+        return Err(format!(
+            "❌ Uploaded WASM {} has hash {}, not the --wasm-hash {}",
+            path, uploaded, args.wasm_hash
+        ));
+    }
+
+    println!("✅ Uploaded {} as {}", path, uploaded);
+    Ok(())
+}
+
+/// Make sure the new WASM is installed, uploading `--wasm` when it is missing
+pub fn ensure_installed(args: &UpgradeArgs) -> Result<(), String> {
+    if check_installed(args)? {
+        return Ok(());
+    }
+
+    // A missing WASM only passes the check with a --wasm file
+    match &args.wasm {
+        Some(path) if is_submitted(args) => upload(args, path),
+        _ => Err(format!(
+            "❌ WASM {} is not installed on {}, and the upgrade isn't submitted so --wasm isn't uploaded",
+            args.wasm_hash, args.network
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_args() -> UpgradeArgs {
+        UpgradeArgs {
+            id: "test_contract".to_string(),
+            wasm_hash: "test_hash".to_string(),
+            ..UpgradeArgs::for_test()
+        }
+    }
+
+    #[test]
+    fn test_has_code_entry() {
+        let installed = r#"{"latest_ledger":1000,"entries":[{"key":{"contract_code":{"hash":"9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"}},"live_until_ledger_seq":5000}]}"#;
+        assert!(has_code_entry(installed).unwrap());
+
+        assert!(!has_code_entry(r#"{"latest_ledger":1000,"entries":[]}"#).unwrap());
+        assert!(!has_code_entry(r#"{"latest_ledger":1000}"#).unwrap());
+        assert!(has_code_entry("not json").is_err());
+    }
+
+    #[test]
+    fn test_hash_wasm() {
+        assert_eq!(
+            hash_wasm(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_verify_wasm_file() {
+        let path = std::env::temp_dir().join("stellar-upgrader-test-verify.wasm");
+        std::fs::write(&path, b"\0asm").unwrap();
+        let path = path.to_str().unwrap();
+        let hash = hash_wasm(b"\0asm");
+
+        assert!(verify_wasm_file(path, &hash).is_ok());
+        assert!(verify_wasm_file(path, &hash.to_uppercase()).is_ok());
+        assert!(verify_wasm_file(path, &"0".repeat(64))
+            .unwrap_err()
+            .contains("doesn't match --wasm-hash"));
+        assert!(verify_wasm_file("/nonexistent/contract.wasm", &hash).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_extract_uploaded_hash() {
+        let output = "ℹ️  Simulating install transaction…\n9AB3011A533A116F82F99EBCD00E72CDCA5E42159AACA379FD249FDBD982D9FF\n";
+        assert_eq!(
+            extract_uploaded_hash(output).unwrap(),
+            "9ab3011a533a116f82f99ebcd00e72cdca5e42159aaca379fd249fdbd982d9ff"
+        );
+
+        assert!(extract_uploaded_hash("error").is_err());
+    }
+
+    #[test]
+    fn test_generate_upload_command() {
+        let args = create_test_args();
+
+        assert_eq!(
+            generate_upload_command(&args, "target/contract.wasm"),
+            "stellar contract upload --wasm target/contract.wasm --source alice --network testnet"
+        );
+    }
+
+    #[test]
+    fn test_code_source() {
+        let mut args = create_test_args();
+        assert_eq!(
+            code_source(&args, "test_hash"),
+            "--wasm-hash test_hash --network testnet"
+        );
+
+        // The new code is read from the --wasm file, which may not be uploaded yet
+        args.wasm = Some("target/contract.wasm".to_string());
+        assert_eq!(
+            code_source(&args, "test_hash"),
+            "--wasm target/contract.wasm"
+        );
+        assert_eq!(
+            code_source(&args, "old_hash"),
+            "--wasm-hash old_hash --network testnet"
+        );
+    }
+}